@@ -1,8 +1,14 @@
 //! Git command types: `AddOptions`, `PushOptions`, etc.
 
-use crate::{CommandOptions, GitError, Repository, Result};
+use crate::{
+    conflict_error, corrupt_commit_graph_error, exec_error, is_exit_one, porcelain, CommandOptions,
+    CommandOutput, GitError, RefUpdate, Repository, Result, Status,
+};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
 use failure::ResultExt;
 use std::process::{self, Command, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// `git add` command.
 ///
@@ -24,6 +30,9 @@ pub struct AddOptions {
     all: Option<bool>,
     chmod: Option<bool>,
     pathspecs: Vec<String>,
+    pathspec_file: String,
+    pathspec_file_nul: bool,
+    extra_args: Vec<String>,
 }
 
 impl AddOptions {
@@ -67,6 +76,29 @@ impl AddOptions {
         }
         self
     }
+
+    /// Add --pathspec-from-file option, reading pathspecs from `file`
+    /// (`-` for stdin), one per line.
+    ///
+    /// Use this for very large lists of files, which may exceed the OS limit
+    /// on command line length. When set, any [`pathspec`] is ignored.
+    ///
+    /// [`pathspec`]: #method.pathspec
+    pub fn pathspec_from_file(&mut self, file: &str) -> &mut AddOptions {
+        self.pathspec_file = file.to_string();
+        self
+    }
+
+    /// Add --pathspec-file-nul option, separating the entries of the
+    /// [`pathspec_from_file`] file by NUL instead of newline.
+    ///
+    /// Needed for file names containing newlines, and it disables quoting.
+    ///
+    /// [`pathspec_from_file`]: #method.pathspec_from_file
+    pub fn pathspec_file_nul(&mut self, val: bool) -> &mut AddOptions {
+        self.pathspec_file_nul = val;
+        self
+    }
 }
 
 impl CommandOptions for AddOptions {
@@ -74,32 +106,161 @@ impl CommandOptions for AddOptions {
 
     fn git_args(&self) -> Vec<&str> {
         let mut args = vec!["add"];
-        // add
-        if let Some(all) = self.all {
-            if all {
-                args.push("--all");
-            } else {
-                args.push("--no-all");
+        push_flag_pair(&mut args, "--all", "--no-all", self.all);
+        push_flag_pair(&mut args, "--chmod=+x", "--chmod=-x", self.chmod);
+        // pathspec
+        if !self.pathspec_file.is_empty() {
+            args.push("--pathspec-from-file");
+            args.push(&self.pathspec_file);
+            push_flag(&mut args, "--pathspec-file-nul", self.pathspec_file_nul);
+        } else {
+            for p in &self.pathspecs {
+                args.push(p);
             }
         }
-        // chmode
-        if let Some(chmod) = self.chmod {
-            if chmod {
-                args.push("--chmod=+x");
-            } else {
-                args.push("--chmod=-x");
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, _out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git branch` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::BranchOptions;
+///
+/// let repo = Repository::new();
+/// let merged = BranchOptions::list()
+///     .merged("master")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Listing branches returns the short branch names. The `(HEAD detached at
+/// <commit>)` line git lists with a detached HEAD is not included. The
+/// upstream actions return nothing, and apply to the current branch unless a
+/// [`branch`] is set.
+///
+/// [`branch`]: #method.branch
+#[derive(Debug, Default)]
+pub struct BranchOptions {
+    action: String,
+    upstream: String,
+    contains: String,
+    merged: String,
+    no_merged: String,
+    branch: String,
+    extra_args: Vec<String>,
+}
+
+impl BranchOptions {
+    /// Create a new set of `git branch --list` options.
+    pub fn list() -> BranchOptions {
+        BranchOptions {
+            action: "list".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git branch --set-upstream-to=<upstream>` options.
+    pub fn set_upstream_to(upstream: &str) -> BranchOptions {
+        BranchOptions {
+            action: "set-upstream-to".to_string(),
+            upstream: format!("--set-upstream-to={}", upstream),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git branch --unset-upstream` options.
+    pub fn unset_upstream() -> BranchOptions {
+        BranchOptions {
+            action: "unset-upstream".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add --contains <commit> option, listing only branches containing the commit.
+    pub fn contains(&mut self, commit: &str) -> &mut BranchOptions {
+        self.contains = commit.to_string();
+        self
+    }
+
+    /// Add --merged <commit> option, listing only branches merged into the commit.
+    pub fn merged(&mut self, commit: &str) -> &mut BranchOptions {
+        self.merged = commit.to_string();
+        self
+    }
+
+    /// Add --no-merged <commit> option, listing only branches not merged into the commit.
+    pub fn no_merged(&mut self, commit: &str) -> &mut BranchOptions {
+        self.no_merged = commit.to_string();
+        self
+    }
+
+    /// Set <branchname> parameter of the upstream actions.
+    pub fn branch(&mut self, name: &str) -> &mut BranchOptions {
+        self.branch = name.to_string();
+        self
+    }
+}
+
+impl CommandOptions for BranchOptions {
+    type Output = Vec<String>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["branch"];
+        match self.action.as_str() {
+            "list" => {
+                args.push("--list");
+                args.push("--format=%(refname)");
             }
+            "set-upstream-to" => args.push(&self.upstream),
+            "unset-upstream" => args.push("--unset-upstream"),
+            _ => (),
         }
-        // pathspec
-        for p in &self.pathspecs {
-            args.push(&p);
+        if !self.contains.is_empty() {
+            args.push("--contains");
+            args.push(&self.contains);
+        }
+        if !self.merged.is_empty() {
+            args.push("--merged");
+            args.push(&self.merged);
+        }
+        if !self.no_merged.is_empty() {
+            args.push("--no-merged");
+            args.push(&self.no_merged);
+        }
+        if !self.branch.is_empty() {
+            args.push(&self.branch);
         }
         args
     }
 
     #[inline]
-    fn parse_output(&self, _out: &str) -> Result<Self::Output> {
-        Ok(())
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        // Full refnames tell branches apart from the detached HEAD line,
+        // remote branches are listed with -r or -a
+        Ok(out
+            .lines()
+            .filter_map(|l| l.strip_prefix("refs/heads/").or_else(|| l.strip_prefix("refs/remotes/")))
+            .map(String::from)
+            .collect())
     }
 }
 
@@ -121,6 +282,14 @@ pub struct CloneOptions {
     origin: Option<String>,
     url: Option<String>,
     dir: Option<String>,
+    git: Option<PathBuf>,
+    // Complete --recurse-submodules[=<pathspec>] arguments
+    recurse_submodules: Vec<String>,
+    // Complete --jobs=<n> argument
+    jobs: String,
+    // Complete --filter=<spec> argument
+    filter: String,
+    extra_args: Vec<String>,
 }
 
 impl CloneOptions {
@@ -149,43 +318,102 @@ impl CloneOptions {
         self
     }
 
-    /// Clone the repository. `repository` is the repo URL.
-    pub fn run(&self, repository: &str) -> Result<Repository> {
-        // Setup git arguments
-        let mut args = vec!["clone"];
-        if let Some(branch) = &self.branch {
-            args.push("--branch");
-            args.push(branch.as_str());
-        }
-        if let Some(origin) = &self.origin {
-            args.push("--origin");
-            args.push(origin.as_str());
-        }
-        args.push(repository);
-        if let Some(dir) = &self.dir {
-            args.push(dir.as_str());
+    /// Add --recurse-submodules option, initializing and cloning all
+    /// submodules after the clone.
+    pub fn recurse_submodules(&mut self, val: bool) -> &mut Self {
+        self.recurse_submodules.clear();
+        if val {
+            self.recurse_submodules.push("--recurse-submodules".to_string());
         }
+        self
+    }
+
+    /// Add --recurse-submodules=<pathspec> option, initializing and cloning
+    /// only the submodules matching the pathspec. Can be added several times.
+    pub fn recurse_submodules_path(&mut self, pathspec: &str) -> &mut Self {
+        self.recurse_submodules.retain(|a| a != "--recurse-submodules");
+        self.recurse_submodules.push(format!("--recurse-submodules={}", pathspec));
+        self
+    }
+
+    /// Add --jobs=<n> option, fetching up to `n` submodules in parallel.
+    pub fn jobs(&mut self, n: u32) -> &mut Self {
+        self.jobs = format!("--jobs={}", n);
+        self
+    }
+
+    /// Add --filter=<spec> option, making a partial clone without the
+    /// objects excluded by the filter, like `blob:none` for a blobless or
+    /// `tree:0` for a treeless clone. Missing objects are fetched on demand.
+    ///
+    /// Requires git 2.19 or newer, and a server supporting partial clone.
+    pub fn filter(&mut self, spec: &str) -> &mut Self {
+        self.filter = format!("--filter={}", spec);
+        self
+    }
+
+    /// Set the git executable, which is also used by the cloned repository.
+    /// By default `git` is looked up in `PATH`.
+    pub fn git_binary<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.git = Some(path.as_ref().to_path_buf());
+        self
+    }
 
+    /// Clone the repository. `repository` is the repo URL.
+    pub fn run(&self, repository: &str) -> Result<Repository> {
         // Run command
-        let mut cmd = Command::new("git");
-        (&mut cmd).args(&args);
-        let out = cmd.output().context("git execution failed")?;
+        let args = self.clone_args(repository);
+        let program = self.git.as_deref().unwrap_or_else(|| Path::new("git"));
+        let mut cmd = Command::new(program);
+        cmd.args(&args);
+        let out = cmd.output().map_err(|e| exec_error(e, program))?;
 
         if out.status.success() {
-            if let Some(dir) = &self.dir {
-                Ok(Repository::at(dir)?)
+            let mut repo = if let Some(dir) = &self.dir {
+                Repository::at(dir)?
             } else {
-                Ok(Repository::new())
+                Repository::new()
+            };
+            if let Some(git) = &self.git {
+                repo.git_binary(git);
             }
+            Ok(repo)
         } else {
             Err(GitError {
                 cmd: "git clone".to_string(),
                 code: out.status.code(),
+                stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
                 stderr: format_err!("{}", std::str::from_utf8(&out.stderr)?),
             }
             .into())
         }
     }
+
+    // Arguments of git clone, including the extra arguments.
+    fn clone_args<'a>(&'a self, repository: &'a str) -> Vec<&'a str> {
+        let mut args = vec!["clone"];
+        if let Some(branch) = &self.branch {
+            args.push("--branch");
+            args.push(branch.as_str());
+        }
+        if let Some(origin) = &self.origin {
+            args.push("--origin");
+            args.push(origin.as_str());
+        }
+        for a in &self.recurse_submodules {
+            args.push(a);
+        }
+        push_arg(&mut args, &self.jobs);
+        push_arg(&mut args, &self.filter);
+        for a in &self.extra_args {
+            args.push(a);
+        }
+        args.push(repository);
+        if let Some(dir) = &self.dir {
+            args.push(dir.as_str());
+        }
+        args
+    }
 }
 
 /// `git commit` command.
@@ -204,13 +432,51 @@ impl CloneOptions {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// The message options [`message`], [`reuse_message`], [`reedit_message`]
+/// and [`fixup`] are mutually exclusive, and git will refuse to commit if more
+/// than one is set. [`squash`] may be combined with an explicit message.
+///
+/// Running the command fails, without running git, if no message source is
+/// set, since git would wait for the message to be written in an editor.
+/// For the same reason `-e` and `--edit` are refused in the extra arguments.
+/// Use [`validate`] to check the options before running.
+///
+/// Unlike [`Repository::commit`], --allow-empty isn't set by default, so
+/// committing without any changes fails. Use [`new_like_repository`] to get
+/// the same behaviour as [`Repository::commit`].
+///
+/// [`message`]: #method.message
+/// [`reuse_message`]: #method.reuse_message
+/// [`reedit_message`]: #method.reedit_message
+/// [`fixup`]: #method.fixup
+/// [`squash`]: #method.squash
+/// [`new_like_repository`]: #method.new_like_repository
+/// [`validate`]: #method.validate
+/// [`Repository::commit`]: ../struct.Repository.html#method.commit
 #[derive(Debug, Default)]
 pub struct CommitOptions {
     all: bool,
     allow_empty: bool,
+    allow_empty_message: bool,
     amend: bool,
+    no_edit: bool,
+    reset_author: bool,
+    only: bool,
     files: Vec<String>,
+    pathspec_file: String,
+    pathspec_file_nul: bool,
     msg: String,
+    reuse_message: String,
+    reedit_message: String,
+    fixup: String,
+    squash: String,
+    // Complete --cleanup=<mode> argument
+    cleanup: String,
+    // Complete --date=<date> argument
+    date: String,
+    config: Vec<String>,
+    extra_args: Vec<String>,
 }
 
 impl CommitOptions {
@@ -221,6 +487,17 @@ impl CommitOptions {
         }
     }
 
+    /// Create a new set of `git commit` options with --allow-empty set,
+    /// matching [`Repository::commit`].
+    ///
+    /// [`Repository::commit`]: ../struct.Repository.html#method.commit
+    pub fn new_like_repository() -> CommitOptions {
+        CommitOptions {
+            allow_empty: true,
+            ..Default::default()
+        }
+    }
+
     /// Add --all options.
     pub fn all(&mut self, val: bool) -> &mut CommitOptions {
         self.all = val;
@@ -233,12 +510,56 @@ impl CommitOptions {
         self
     }
 
+    /// Add --allow-empty-message option.
+    ///
+    /// If no message is set, and the message isn't taken from another commit,
+    /// an empty message is passed with -m.
+    pub fn allow_empty_message(&mut self, val: bool) -> &mut CommitOptions {
+        self.allow_empty_message = val;
+        self
+    }
+
     /// Add --amend option.
     pub fn amend(&mut self, val: bool) -> &mut CommitOptions {
         self.amend = val;
         self
     }
 
+    /// Add --no-edit option, keeping the message of the amended commit.
+    ///
+    /// Only meaningful together with [`amend`] or [`reedit_message`].
+    ///
+    /// [`amend`]: #method.amend
+    /// [`reedit_message`]: #method.reedit_message
+    pub fn no_edit(&mut self, val: bool) -> &mut CommitOptions {
+        self.no_edit = val;
+        self
+    }
+
+    /// Add --reset-author option, taking over authorship of the amended
+    /// commit and resetting the author date.
+    ///
+    /// Only meaningful together with [`amend`].
+    ///
+    /// [`amend`]: #method.amend
+    pub fn reset_author(&mut self, val: bool) -> &mut CommitOptions {
+        self.reset_author = val;
+        self
+    }
+
+    /// Add --only option.
+    ///
+    /// Only the added [`files`] are committed, using their content in the work
+    /// tree, while changes already staged for other paths are left in the
+    /// index. This is what git does by default when paths are given, but
+    /// makes it explicit. Without any files only the message can be amended.
+    ///
+    /// [`files`]: #method.files
+    pub fn only(&mut self, val: bool) -> &mut CommitOptions {
+        self.only = val;
+        self
+    }
+
     /// Add file to commit command.
     pub fn file(&mut self, file: impl ToString) -> &mut CommitOptions {
         self.files.push(file.to_string());
@@ -257,11 +578,154 @@ impl CommitOptions {
         self
     }
 
+    /// Add --pathspec-from-file option, reading the files to commit from
+    /// `file` (`-` for stdin), one per line.
+    ///
+    /// Use this for very large lists of files, which may exceed the OS limit
+    /// on command line length. When set, any added [`files`] are ignored.
+    ///
+    /// [`files`]: #method.files
+    pub fn pathspec_from_file(&mut self, file: &str) -> &mut CommitOptions {
+        self.pathspec_file = file.to_string();
+        self
+    }
+
+    /// Add --pathspec-file-nul option, separating the entries of the
+    /// [`pathspec_from_file`] file by NUL instead of newline.
+    ///
+    /// Needed for file names containing newlines, and it disables quoting.
+    ///
+    /// [`pathspec_from_file`]: #method.pathspec_from_file
+    pub fn pathspec_file_nul(&mut self, val: bool) -> &mut CommitOptions {
+        self.pathspec_file_nul = val;
+        self
+    }
+
     /// Set commit message.
     pub fn message(&mut self, msg: &str) -> &mut CommitOptions {
         self.msg = msg.to_owned();
         self
     }
+
+    /// Add -C <commit> option, reusing the message of an existing commit.
+    pub fn reuse_message(&mut self, commit: &str) -> &mut CommitOptions {
+        self.reuse_message = commit.to_owned();
+        self
+    }
+
+    /// Add -c <commit> option. Like [`reuse_message`], but invokes the editor.
+    ///
+    /// [`reuse_message`]: #method.reuse_message
+    pub fn reedit_message(&mut self, commit: &str) -> &mut CommitOptions {
+        self.reedit_message = commit.to_owned();
+        self
+    }
+
+    /// Add --fixup <commit> option, creating a fixup commit for `git rebase --autosquash`.
+    pub fn fixup(&mut self, commit: &str) -> &mut CommitOptions {
+        self.fixup = commit.to_owned();
+        self
+    }
+
+    /// Add --squash <commit> option, creating a squash commit for `git rebase --autosquash`.
+    pub fn squash(&mut self, commit: &str) -> &mut CommitOptions {
+        self.squash = commit.to_owned();
+        self
+    }
+
+    /// Add --cleanup=<mode> option, controlling how the message is cleaned
+    /// up: `strip`, `whitespace`, `verbatim`, `scissors` or `default`.
+    ///
+    /// Use `verbatim` or `whitespace` to keep lines starting with `#`, like
+    /// in templated messages, which are stripped as comments by default.
+    pub fn cleanup(&mut self, mode: &str) -> &mut CommitOptions {
+        self.cleanup = format!("--cleanup={}", mode);
+        self
+    }
+
+    /// Add --date=<date> option, overriding the author date. Any format
+    /// git understands can be used, like `2020-06-13T12:00:00+02:00` or
+    /// `@1592042400 +0200`.
+    ///
+    /// Only the author date is set. The committer date is still taken from
+    /// `GIT_COMMITTER_DATE` or the clock.
+    pub fn date(&mut self, date: &str) -> &mut CommitOptions {
+        self.date = format!("--date={}", date);
+        self
+    }
+
+    /// Add --date=<date> option with the current system time, as
+    /// `@<seconds since epoch> +0000`.
+    ///
+    /// This overrides an author date set with `GIT_AUTHOR_DATE`. Git doesn't
+    /// read `SOURCE_DATE_EPOCH`, and neither does this method, so the date
+    /// is always the current time. For reproducible commits pass the epoch
+    /// to [`date`] instead, like `@1592042400 +0000`.
+    ///
+    /// [`date`]: #method.date
+    pub fn date_now(&mut self) -> &mut CommitOptions {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.date(&format!("@{} +0000", secs))
+    }
+
+    /// Set the program used for signing, with a `-c gpg.program=<program>`
+    /// override for this commit only.
+    pub fn gpg_program(&mut self, program: &str) -> &mut CommitOptions {
+        set_config(&mut self.config, "gpg.program", program);
+        self
+    }
+
+    /// Set the signature format, `openpgp`, `x509` or `ssh`, with a
+    /// `-c gpg.format=<format>` override for this commit only.
+    pub fn gpg_format(&mut self, format: &str) -> &mut CommitOptions {
+        set_config(&mut self.config, "gpg.format", format);
+        self
+    }
+
+    // Returns true if the message is taken from an existing commit.
+    fn reuses_message(&self) -> bool {
+        !self.reuse_message.is_empty()
+            || !self.reedit_message.is_empty()
+            || !self.fixup.is_empty()
+            || self.no_edit
+    }
+
+    /// Check that the commit can be made without opening an editor, which is
+    /// what [`run`] does before running git.
+    ///
+    /// Fails if no message source is set, or if the extra arguments contain
+    /// `-e` or `--edit`.
+    ///
+    /// [`run`]: #method.run
+    pub fn validate(&self) -> Result<()> {
+        if self.extra_args.iter().any(|a| a == "-e" || a == "--edit") {
+            bail!("git commit would open an editor, -e and --edit are not allowed");
+        }
+        if self.needs_editor() {
+            bail!("git commit would open an editor, set a message or use no_edit when amending");
+        }
+        Ok(())
+    }
+
+    // Returns true if git would open an editor for the commit message.
+    fn needs_editor(&self) -> bool {
+        let message_arg = self.extra_args.iter().any(|a| {
+            ["-m", "-F", "--message", "--file", "--no-edit"]
+                .iter()
+                .any(|opt| a.starts_with(opt))
+        });
+        if !self.reedit_message.is_empty() {
+            // -c always opens the editor, unless told otherwise
+            return !self.no_edit && !self.extra_args.iter().any(|a| a == "--no-edit");
+        }
+        self.msg.is_empty()
+            && !self.allow_empty_message
+            && !self.reuses_message()
+            && !message_arg
+    }
 }
 
 impl CommandOptions for CommitOptions {
@@ -269,483 +733,2730 @@ impl CommandOptions for CommitOptions {
 
     fn git_args(&self) -> Vec<&str> {
         let mut args = vec!["commit", "-q"];
-        if !self.msg.is_empty() {
+        if !self.msg.is_empty() || (self.allow_empty_message && !self.reuses_message()) {
             args.push("-m");
             args.push(&self.msg);
         }
-        if self.all {
-            args.push("--all");
+        if !self.reuse_message.is_empty() {
+            args.push("-C");
+            args.push(&self.reuse_message);
         }
-        if self.allow_empty {
-            args.push("--allow-empty");
+        if !self.reedit_message.is_empty() {
+            args.push("-c");
+            args.push(&self.reedit_message);
         }
-        if self.amend {
-            args.push("--amend");
+        if !self.fixup.is_empty() {
+            args.push("--fixup");
+            args.push(&self.fixup);
         }
-        for file in &self.files {
-            args.push(&file);
+        if !self.squash.is_empty() {
+            args.push("--squash");
+            args.push(&self.squash);
+        }
+        push_arg(&mut args, &self.cleanup);
+        push_arg(&mut args, &self.date);
+        push_flag(&mut args, "--all", self.all);
+        push_flag(&mut args, "--allow-empty", self.allow_empty);
+        push_flag(&mut args, "--allow-empty-message", self.allow_empty_message);
+        push_flag(&mut args, "--amend", self.amend);
+        push_flag(&mut args, "--no-edit", self.no_edit);
+        push_flag(&mut args, "--reset-author", self.reset_author);
+        push_flag(&mut args, "--only", self.only);
+        if !self.pathspec_file.is_empty() {
+            args.push("--pathspec-from-file");
+            args.push(&self.pathspec_file);
+            push_flag(&mut args, "--pathspec-file-nul", self.pathspec_file_nul);
+        } else {
+            for file in &self.files {
+                args.push(file);
+            }
         }
         args
     }
 
+    #[inline]
+    fn config(&self) -> &[String] {
+        &self.config
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     #[inline]
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
         Ok(())
     }
+
+    /// Run the commit, failing without running git if the commit message
+    /// would have to be written in an editor.
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        self.validate()?;
+        self.parse_output(&repo.run(self.args())?)
+    }
 }
 
-/// `git notes` command.
+/// `git commit-graph` command.
 ///
 /// ```rust,no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// use mhgit::{CommandOptions, Repository};
-/// use mhgit::commands::NotesOptions;
+/// use mhgit::{CommandOptions, CorruptCommitGraphError, Repository};
+/// use mhgit::commands::CommitGraphOptions;
 ///
 /// let repo = Repository::new();
-/// NotesOptions::add()
-///     .message("My note")
-///     .object("HEAD")
-///     .run(&repo)?;
+/// if let Err(e) = CommitGraphOptions::verify().run(&repo) {
+///     if e.downcast_ref::<CorruptCommitGraphError>().is_none() {
+///         return Err(e.into());
+///     }
+///     CommitGraphOptions::write(true).run(&repo)?;
+/// }
 /// # Ok(())
 /// # }
 /// ```
+///
+/// If [`verify`] finds the commit-graph to be corrupt a
+/// [`CorruptCommitGraphError`] is returned. A missing commit-graph isn't
+/// corrupt.
+///
+/// [`verify`]: #method.verify
+/// [`CorruptCommitGraphError`]: ../struct.CorruptCommitGraphError.html
 #[derive(Debug, Default)]
-pub struct NotesOptions {
+pub struct CommitGraphOptions {
     action: String,
-    msg: String,
-    object: String,
+    reachable: bool,
+    extra_args: Vec<String>,
 }
 
-impl NotesOptions {
-    /// Create a new set of `git notes add` options.
-    pub fn add() -> NotesOptions {
-        NotesOptions {
-            action: "add".to_owned(),
-            ..Default::default()
-        }
-    }
-
-    /// Create a new set of `git notes append` options.
-    pub fn append() -> NotesOptions {
-        NotesOptions {
-            action: "append".to_owned(),
+impl CommitGraphOptions {
+    /// Create a new set of `git commit-graph write` options. With
+    /// `reachable` the --reachable option is added, including all commits
+    /// reachable from any ref, instead of the commits in packs.
+    pub fn write(reachable: bool) -> CommitGraphOptions {
+        CommitGraphOptions {
+            action: "write".to_string(),
+            reachable,
             ..Default::default()
         }
     }
 
-    /// Create a new set of `git notes remove` options.
-    pub fn remove() -> NotesOptions {
-        NotesOptions {
-            action: "remove".to_owned(),
+    /// Create a new set of `git commit-graph verify` options.
+    pub fn verify() -> CommitGraphOptions {
+        CommitGraphOptions {
+            action: "verify".to_string(),
             ..Default::default()
         }
     }
-
-    /// Set commit message.
-    pub fn message(&mut self, msg: &str) -> &mut NotesOptions {
-        self.msg = msg.to_owned();
-        self
-    }
-
-    /// Set object to attach notes to.
-    pub fn object(&mut self, object: &str) -> &mut NotesOptions {
-        self.object = object.to_owned();
-        self
-    }
 }
 
-impl CommandOptions for NotesOptions {
+impl CommandOptions for CommitGraphOptions {
     type Output = ();
 
     fn git_args(&self) -> Vec<&str> {
-        let mut args = vec!["notes", &self.action];
-        if !self.msg.is_empty() {
-            args.push("-m");
-            args.push(&self.msg);
-        }
-        if !self.object.is_empty() {
-            args.push(&self.object);
-        }
+        let mut args = vec!["commit-graph", &self.action];
+        push_flag(&mut args, "--reachable", self.reachable);
         args
     }
 
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     #[inline]
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
         Ok(())
     }
-}
 
-/// `git pull` command.
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        let res = repo.run(self.args());
+        let out = if self.action == "verify" {
+            res.map_err(corrupt_commit_graph_error)?
+        } else {
+            res?
+        };
+        self.parse_output(&out)
+    }
+}
+
+/// `git diff` command.
 ///
 /// ```rust,no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// use mhgit::{CommandOptions, Repository};
-/// use mhgit::commands::PullOptions;
+/// use mhgit::commands::{ColorMode, DiffOptions};
 ///
 /// let repo = Repository::new();
-/// PullOptions::new()
-///     .remote("origin")
-///     .refspec("master")
+/// let diff = DiffOptions::new()
+///     .against("HEAD~1")
+///     .word_diff(true)
+///     .color(ColorMode::Always)
 ///     .run(&repo)?;
+/// println!("{}", diff.patch);
 /// # Ok(())
 /// # }
 /// ```
+///
+/// By default the work tree is compared to the index, like `git diff`.
+/// With [`name_status`] the changed files are listed in [`Diff::files`],
+/// instead of a patch.
+///
+/// `git diff` ignores `--first-parent`. To get the changes a merge brought
+/// into the mainline, compare the merge with its first parent, like
+/// `.against("<merge>^1").against("<merge>")`.
+///
+/// [`name_status`]: #method.name_status
+/// [`Diff::files`]: struct.Diff.html#structfield.files
 #[derive(Debug, Default)]
-pub struct PullOptions {
-    allow_unrelated: bool,
-    repository: String,
-    refspecs: Vec<String>,
+pub struct DiffOptions {
+    cached: bool,
+    word_diff: bool,
+    name_status: bool,
+    find_renames: String,
+    find_copies: String,
+    color: Option<ColorMode>,
+    merge_base: bool,
+    // Complete <rev>...<rev> argument, with merge_base
+    range: String,
+    revisions: Vec<String>,
+    pathspecs: Vec<String>,
+    extra_args: Vec<String>,
 }
 
-impl PullOptions {
-    /// Create a new set of `git pull` options.
-    pub fn new() -> PullOptions {
-        PullOptions {
+/// When git should color its output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// Always color the output.
+    Always,
+    /// Never color the output.
+    Never,
+    /// Color the output when writing to a terminal, which captured output
+    /// never is.
+    Auto,
+}
+
+/// Output of git diff.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Diff {
+    /// The patch text
+    pub patch: String,
+
+    /// Changed files, only with `--name-status`
+    pub files: Vec<DiffFile>,
+}
+
+/// A changed file, as listed by git diff --name-status.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DiffFile {
+    /// Status letter: `A` added, `M` modified, `D` deleted, `R` renamed,
+    /// `C` copied, etc.
+    pub status: char,
+
+    /// Similarity percentage of renamed and copied files, otherwise 0
+    pub score: u8,
+
+    /// Path of the file. For renamed and copied files, this is the new path.
+    pub path: String,
+
+    /// Old path of renamed and copied files
+    pub orig_path: Option<String>,
+}
+
+impl DiffOptions {
+    /// Create a new set of `git diff` options.
+    pub fn new() -> DiffOptions {
+        DiffOptions {
             ..Default::default()
         }
     }
 
-    /// Add --allow-unrelated option.
-    pub fn allow_unrelated(&mut self, val: bool) -> &mut PullOptions {
-        self.allow_unrelated = val;
+    /// Add --cached option, comparing the index instead of the work tree.
+    pub fn cached(&mut self, val: bool) -> &mut DiffOptions {
+        self.cached = val;
         self
     }
 
-    /// Add refspec to pull command.
-    pub fn refspec(&mut self, file: impl ToString) -> &mut PullOptions {
-        self.refspecs.push(file.to_string());
+    /// Compare against a commit. Add a second commit to compare the two.
+    pub fn against(&mut self, rev: &str) -> &mut DiffOptions {
+        self.revisions.push(rev.to_string());
+        self.update_range();
         self
     }
 
-    /// Add multiple refspecs to pull command.
-    pub fn refspecs<I, S>(&mut self, files: I) -> &mut PullOptions
-    where
-        I: IntoIterator<Item = S>,
-        S: ToString,
-    {
-        for f in files {
-            self.refspecs.push(f.to_string());
-        }
+    /// Compare the commits as `A...B`, showing the changes on `B` since it
+    /// forked from `A`, rather than the difference between `A` and `B`.
+    ///
+    /// With a single commit given to [`against`], it's compared with HEAD,
+    /// like `A...HEAD`. Without any commits this has no effect.
+    ///
+    /// [`against`]: #method.against
+    pub fn merge_base(&mut self, val: bool) -> &mut DiffOptions {
+        self.merge_base = val;
+        self.update_range();
         self
     }
 
-    /// Set remote repository source.
-    pub fn remote(&mut self, repo: impl ToString) -> &mut PullOptions {
-        self.repository = repo.to_string();
+    // Update the three-dot range argument used with merge_base.
+    fn update_range(&mut self) {
+        self.range = match (self.merge_base, self.revisions.as_slice()) {
+            (true, [a]) => format!("{}...HEAD", a),
+            (true, [a, b]) => format!("{}...{}", a, b),
+            _ => String::new(),
+        };
+    }
+
+    /// Add a pathspec, limiting the diff to matching paths.
+    pub fn pathspec(&mut self, pathspec: impl ToString) -> &mut DiffOptions {
+        self.pathspecs.push(pathspec.to_string());
+        self
+    }
+
+    /// Add --word-diff option, showing changed words rather than lines in
+    /// the patch, marked like `[-removed-]{+added+}`.
+    pub fn word_diff(&mut self, val: bool) -> &mut DiffOptions {
+        self.word_diff = val;
+        self
+    }
+
+    /// Add --name-status option, listing the changed files instead of a patch.
+    pub fn name_status(&mut self, val: bool) -> &mut DiffOptions {
+        self.name_status = val;
+        self
+    }
+
+    /// Add -M[<n>%] option, detecting renamed files with at least `n`
+    /// percent similarity. Git's default threshold is 50%.
+    pub fn find_renames(&mut self, threshold: Option<u8>) -> &mut DiffOptions {
+        self.find_renames = match threshold {
+            Some(n) => format!("-M{}%", n),
+            None => "-M".to_string(),
+        };
+        self
+    }
+
+    /// Add -C[<n>%] option, detecting copied files with at least `n`
+    /// percent similarity. Renames are detected as well.
+    pub fn find_copies(&mut self, threshold: Option<u8>) -> &mut DiffOptions {
+        self.find_copies = match threshold {
+            Some(n) => format!("-C{}%", n),
+            None => "-C".to_string(),
+        };
+        self
+    }
+
+    /// Add --color=<mode> option.
+    ///
+    /// Colors are ANSI escape codes in the patch text, which must be
+    /// rendered by a terminal or stripped before displaying it elsewhere.
+    pub fn color(&mut self, mode: ColorMode) -> &mut DiffOptions {
+        self.color = Some(mode);
         self
     }
 }
 
-impl CommandOptions for PullOptions {
-    type Output = ();
+impl CommandOptions for DiffOptions {
+    type Output = Diff;
 
     fn git_args(&self) -> Vec<&str> {
-        let mut args = vec!["pull", "-q"];
-        if self.allow_unrelated {
-            args.push("--allow-unrelated");
+        let mut args = vec!["diff"];
+        if self.cached {
+            args.push("--cached");
         }
-        if !self.repository.is_empty() {
-            args.push(&self.repository);
+        if self.word_diff {
+            args.push("--word-diff");
         }
-        for rs in &self.refspecs {
-            args.push(&rs);
+        if self.name_status {
+            args.push("--name-status");
+            args.push("-z");
+        }
+        if !self.find_renames.is_empty() {
+            args.push(&self.find_renames);
+        }
+        if !self.find_copies.is_empty() {
+            args.push(&self.find_copies);
+        }
+        match self.color {
+            Some(ColorMode::Always) => args.push("--color=always"),
+            Some(ColorMode::Never) => args.push("--color=never"),
+            Some(ColorMode::Auto) => args.push("--color=auto"),
+            None => (),
+        }
+        if !self.range.is_empty() {
+            args.push(&self.range);
+        } else {
+            for rev in &self.revisions {
+                args.push(rev);
+            }
+        }
+        args.push("--");
+        for p in &self.pathspecs {
+            args.push(p);
         }
         args
     }
 
     #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
-        Ok(())
+        if !self.name_status {
+            return Ok(Diff {
+                patch: out.to_string(),
+                ..Default::default()
+            });
+        }
+        let files = parse_name_status(out)?;
+        Ok(Diff {
+            files,
+            ..Default::default()
+        })
     }
 }
 
-/// `git push` command.
+/// `git fetch` command.
 ///
 /// ```rust,no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// use mhgit::{CommandOptions, Repository};
-/// use mhgit::commands::PushOptions;
+/// use mhgit::commands::FetchOptions;
 ///
 /// let repo = Repository::new();
-/// PushOptions::new()
+/// FetchOptions::new()
+///     .all(true)
+///     .prune(true)
+///     .jobs(4)
 ///     .run(&repo)?;
 /// # Ok(())
 /// # }
 /// ```
 #[derive(Debug, Default)]
-pub struct PushOptions {
+pub struct FetchOptions {
     all: bool,
-    tags: bool,
-    force: bool,
-    set_upstream: bool,
+    prune: bool,
+    porcelain: bool,
+    // Complete --jobs=<n> argument
+    jobs: String,
+    // Complete --filter=<spec> argument
+    filter: String,
     repository: String,
     refspecs: Vec<String>,
+    extra_args: Vec<String>,
 }
 
-impl PushOptions {
-    /// Create a new set of `git tag` options.
-    pub fn new() -> PushOptions {
-        PushOptions {
+impl FetchOptions {
+    /// Create a new set of `git fetch` options.
+    pub fn new() -> FetchOptions {
+        FetchOptions {
             ..Default::default()
         }
     }
 
-    /// Add --all option.
-    pub fn all(&mut self, val: bool) -> &mut PushOptions {
+    /// Add --all option, fetching all remotes.
+    pub fn all(&mut self, val: bool) -> &mut FetchOptions {
         self.all = val;
         self
     }
 
-    /// Add --tags option.
-    pub fn tags(&mut self, val: bool) -> &mut PushOptions {
-        self.tags = val;
+    /// Add --prune option, removing remote-tracking refs which no longer
+    /// exist on the remote.
+    pub fn prune(&mut self, val: bool) -> &mut FetchOptions {
+        self.prune = val;
         self
     }
 
-    /// Add --force option.
-    pub fn force(&mut self, val: bool) -> &mut PushOptions {
-        self.force = val;
+    /// Add --porcelain option, returning a [`RefUpdate`] for each updated
+    /// ref. Requires git 2.41 or newer.
+    ///
+    /// [`RefUpdate`]: ../struct.RefUpdate.html
+    pub fn porcelain(&mut self, val: bool) -> &mut FetchOptions {
+        self.porcelain = val;
         self
     }
 
-    /// Add --set-upstream option.
-    pub fn set_upstream(&mut self, val: bool) -> &mut PushOptions {
-        self.set_upstream = val;
+    /// Add --jobs=<n> option, fetching up to `n` remotes or submodules in
+    /// parallel.
+    pub fn jobs(&mut self, n: u32) -> &mut FetchOptions {
+        self.jobs = format!("--jobs={}", n);
         self
     }
 
-    /// Add refspec to push command.
-    pub fn refspec(&mut self, file: impl ToString) -> &mut PushOptions {
-        self.refspecs.push(file.to_string());
+    /// Add --filter=<spec> option, like `blob:none`, leaving out the objects
+    /// excluded by the filter. Used to fetch into a partial clone.
+    ///
+    /// Requires git 2.19 or newer, and a server supporting partial clone.
+    pub fn filter(&mut self, spec: &str) -> &mut FetchOptions {
+        self.filter = format!("--filter={}", spec);
         self
     }
 
-    /// Add multiple refspecs to push command.
-    pub fn refspecs<I, S>(&mut self, files: I) -> &mut PushOptions
-    where
-        I: IntoIterator<Item = S>,
-        S: ToString,
-    {
-        for f in files {
-            self.refspecs.push(f.to_string());
-        }
+    /// Set remote repository source.
+    pub fn remote(&mut self, repo: impl ToString) -> &mut FetchOptions {
+        self.repository = repo.to_string();
         self
     }
 
-    /// Set remote repository source.
-    pub fn remote(&mut self, repo: impl ToString) -> &mut PushOptions {
-        self.repository = repo.to_string();
+    /// Add refspec to fetch command.
+    pub fn refspec(&mut self, refspec: impl ToString) -> &mut FetchOptions {
+        self.refspecs.push(refspec.to_string());
         self
     }
 }
 
-impl CommandOptions for PushOptions {
-    type Output = ();
+impl CommandOptions for FetchOptions {
+    type Output = Vec<RefUpdate>;
 
     fn git_args(&self) -> Vec<&str> {
-        let mut args = vec!["push", "-q"];
-        if self.all {
-            args.push("--all");
-        }
-        if self.tags {
-            args.push("--tags");
-        }
-        if self.force {
-            args.push("--force");
-        }
-        if self.set_upstream {
-            args.push("--set-upstream");
-        }
-        if !self.repository.is_empty() {
-            args.push(&self.repository);
+        let mut args = vec!["fetch"];
+        if self.porcelain {
+            args.push("--porcelain");
+        } else {
+            args.push("-q");
         }
+        push_flag(&mut args, "--all", self.all);
+        push_flag(&mut args, "--prune", self.prune);
+        push_arg(&mut args, &self.jobs);
+        push_arg(&mut args, &self.filter);
+        push_arg(&mut args, &self.repository);
         for rs in &self.refspecs {
-            args.push(&rs);
+            args.push(rs);
         }
         args
     }
 
     #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
-        Ok(())
+        if !self.porcelain {
+            return Ok(Vec::new());
+        }
+        porcelain::parse_fetch(out)
     }
 }
 
-/// `git remote` command.
+/// `git log` command.
 ///
 /// ```rust,no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// use mhgit::{CommandOptions, Repository};
-/// use mhgit::commands::RemoteOptions;
+/// use mhgit::commands::{CommitFormat, LogOptions};
 ///
 /// let repo = Repository::new();
-/// RemoteOptions::add()
-///     .master("master")
-///     .name("upstream")
-///     .url("https://web.com/myrepo.git")
+/// let commits = LogOptions::new()
+///     .format(CommitFormat::OneLine)
+///     .max_count(10)
 ///     .run(&repo)?;
+/// for commit in commits {
+///     println!("{} {}", commit.oid, commit.subject);
+/// }
 /// # Ok(())
 /// # }
 /// ```
+///
+/// The [`format`] decides which fields of the returned [`Commit`]s are set.
+///
+/// [`format`]: #method.format
+/// [`Commit`]: struct.Commit.html
 #[derive(Debug, Default)]
-pub struct RemoteOptions {
-    action: String,
-    master: String,
-    tags: Option<bool>,
-    name: String,
-    url: String,
+pub struct LogOptions {
+    format: CommitFormat,
+    // Complete --format=<format> argument
+    format_arg: String,
+    // Complete --max-count=<n> argument
+    max_count: String,
+    first_parent: bool,
+    revisions: Vec<String>,
+    extra_args: Vec<String>,
 }
 
-impl RemoteOptions {
-    /// Create a new set of `git remote add` options.
-    pub fn add() -> RemoteOptions {
-        RemoteOptions {
-            action: "add".to_string(),
+/// Commit format of [`LogOptions`].
+///
+/// [`LogOptions`]: struct.LogOptions.html
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CommitFormat {
+    /// Oid and subject.
+    OneLine,
+    /// Oid, author, author date and message. This is the git default.
+    #[default]
+    Medium,
+    /// Oid, author, committer and message.
+    Full,
+    /// Oid, parent oids, author, committer, author date, subject and body.
+    /// Every field of [`Commit`] is set.
+    ///
+    /// [`Commit`]: struct.Commit.html
+    Detailed,
+    /// A raw `--format` string, like `%H %an`. Only the formatted
+    /// [`text`] of the commits is set.
+    ///
+    /// [`text`]: struct.Commit.html#structfield.text
+    Custom(String),
+}
+
+/// A single commit from git log.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Commit {
+    /// Object id (oid) of the commit
+    pub oid: String,
+
+    /// Author, as `name <email>`
+    pub author: String,
+
+    /// Committer, as `name <email>` (only full and detailed format)
+    pub committer: String,
+
+    /// Author date (only medium and detailed format)
+    pub date: String,
+
+    /// Oids of the parent commits, two or more for merges (only detailed
+    /// format)
+    pub parents: Vec<String>,
+
+    /// First line of the commit message
+    pub subject: String,
+
+    /// Commit message after the subject and the following empty line
+    /// (only detailed format)
+    pub body: String,
+
+    /// The commit as formatted by git
+    pub text: String,
+}
+
+// One field per line, with the multi-line body last.
+const DETAILED_FORMAT: &str = "%H%n%P%n%an <%ae>%n%cn <%ce>%n%ad%n%s%n%b";
+
+impl LogOptions {
+    /// Create a new set of `git log` options.
+    pub fn new() -> LogOptions {
+        LogOptions {
             ..Default::default()
         }
     }
 
-    /// Add -m <master> option.
-    pub fn master(&mut self, name: &str) -> &mut RemoteOptions {
-        self.master = name.to_string();
+    /// Set the commit format. The default is [`CommitFormat::Medium`].
+    ///
+    /// [`CommitFormat::Medium`]: enum.CommitFormat.html#variant.Medium
+    pub fn format(&mut self, format: CommitFormat) -> &mut LogOptions {
+        self.format_arg = match &format {
+            CommitFormat::OneLine => String::from("--format=oneline"),
+            CommitFormat::Medium => String::from("--format=medium"),
+            CommitFormat::Full => String::from("--format=full"),
+            CommitFormat::Detailed => format!("--format={}", DETAILED_FORMAT),
+            CommitFormat::Custom(fmt) => format!("--format={}", fmt),
+        };
+        self.format = format;
         self
     }
 
-    /// Add --tags or --no-tags option.
-    pub fn tags(&mut self, val: bool) -> &mut RemoteOptions {
-        self.tags = Some(val);
+    /// Add --max-count=<n> option, limiting the number of commits.
+    pub fn max_count(&mut self, n: usize) -> &mut LogOptions {
+        self.max_count = format!("--max-count={}", n);
         self
     }
 
-    /// Set <name> parameter.
-    pub fn name(&mut self, name: &str) -> &mut RemoteOptions {
-        self.name = name.to_string();
+    /// Add --first-parent option, only following the first parent of merge
+    /// commits. This gives the mainline history, without the commits of
+    /// merged branches.
+    pub fn first_parent(&mut self, val: bool) -> &mut LogOptions {
+        self.first_parent = val;
         self
     }
 
-    /// Set <url> parameter.
-    pub fn url(&mut self, url: &str) -> &mut RemoteOptions {
-        self.url = url.to_string();
+    /// Add a revision or revision range, like `v1.0..HEAD`. HEAD is default.
+    pub fn revision(&mut self, rev: &str) -> &mut LogOptions {
+        self.revisions.push(rev.to_string());
         self
     }
 }
 
-impl CommandOptions for RemoteOptions {
-    type Output = ();
+impl CommandOptions for LogOptions {
+    type Output = Vec<Commit>;
 
     fn git_args(&self) -> Vec<&str> {
-        let mut args = vec!["remote", &self.action];
-        if !self.master.is_empty() {
-            args.push("-m");
-            args.push(&self.master);
+        // Commits are separated by NUL
+        let mut args = vec!["log", "-z", "--no-decorate"];
+        if self.format_arg.is_empty() {
+            args.push("--format=medium");
+        } else {
+            args.push(&self.format_arg);
         }
-        match &self.tags {
-            Some(true) => args.push("--tags"),
-            Some(false) => args.push("--no-tags"),
-            None => (),
+        push_arg(&mut args, &self.max_count);
+        push_flag(&mut args, "--first-parent", self.first_parent);
+        for rev in &self.revisions {
+            args.push(rev);
         }
-        args.push(&self.name);
-        args.push(&self.url);
         args
     }
 
     #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
-        Ok(())
+        let mut commits = Vec::new();
+        for text in out.split('\0') {
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            if text.is_empty() {
+                continue;
+            }
+            let mut commit = Commit {
+                text: text.to_string(),
+                ..Default::default()
+            };
+            match self.format {
+                CommitFormat::OneLine => {
+                    let (oid, subject) = text.split_once(' ').unwrap_or((text, ""));
+                    commit.oid = oid.to_string();
+                    commit.subject = subject.to_string();
+                }
+                CommitFormat::Medium | CommitFormat::Full => {
+                    // Headers, an empty line and the message indented by four spaces
+                    let mut lines = text.lines();
+                    for line in &mut lines {
+                        if line.is_empty() {
+                            break;
+                        }
+                        let (key, val) = line
+                            .split_once([' ', ':'])
+                            .ok_or_else(|| format_err!("bad log format: {}", line))?;
+                        let val = val.trim_start_matches(':').trim().to_string();
+                        match key {
+                            "commit" => commit.oid = val,
+                            "Author" => commit.author = val,
+                            "Commit" => commit.committer = val,
+                            "Date" => commit.date = val,
+                            _ => (),
+                        }
+                    }
+                    if let Some(subject) = lines.next() {
+                        commit.subject = subject.trim_start().to_string();
+                    }
+                    ensure!(!commit.oid.is_empty(), "bad log format: missing commit oid");
+                }
+                CommitFormat::Detailed => {
+                    let fields: Vec<&str> = text.splitn(7, '\n').collect();
+                    ensure!(fields.len() >= 6, "bad log format: {}", text);
+                    commit.oid = fields[0].to_string();
+                    commit.parents = fields[1].split_whitespace().map(String::from).collect();
+                    commit.author = fields[2].to_string();
+                    commit.committer = fields[3].to_string();
+                    commit.date = fields[4].to_string();
+                    commit.subject = fields[5].to_string();
+                    commit.body = fields.get(6).unwrap_or(&"").trim_end_matches('\n').to_string();
+                }
+                CommitFormat::Custom(_) => (),
+            }
+            commits.push(commit);
+        }
+        Ok(commits)
     }
 }
 
-/// `git tag` command.
+/// `git maintenance` command.
+///
+/// Requires git 2.29 or newer. This is the recommended replacement for
+/// running `git gc` manually.
 ///
 /// ```rust,no_run
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// use mhgit::{CommandOptions, Repository};
-/// use mhgit::commands::TagOptions;
+/// use mhgit::commands::MaintenanceOptions;
 ///
 /// let repo = Repository::new();
-/// TagOptions::add()
-///     .msg("A new tag")
-///     .tagname("v0.0")
+/// MaintenanceOptions::run()
+///     .task("commit-graph")
+///     .task("gc")
 ///     .run(&repo)?;
 /// # Ok(())
 /// # }
 /// ```
 #[derive(Debug, Default)]
-pub struct TagOptions {
+pub struct MaintenanceOptions {
     action: String,
-    msg: String,
-    tagname: String,
-    // commit/object
-    object: String,
+    auto: bool,
+    // Complete --task=<task> arguments
+    tasks: Vec<String>,
+    extra_args: Vec<String>,
 }
 
-impl TagOptions {
-    /// Create a new set of `git tag` options.
-    pub fn add() -> TagOptions {
-        TagOptions {
-            action: "add".to_string(),
+impl MaintenanceOptions {
+    /// Create a new set of `git maintenance run` options.
+    pub fn run() -> MaintenanceOptions {
+        MaintenanceOptions {
+            action: "run".to_string(),
             ..Default::default()
         }
     }
 
-    /// Create a new set of `git tag -d` options.
-    pub fn delete() -> TagOptions {
-        TagOptions {
-            action: "delete".to_string(),
-            ..Default::default()
-        }
+    /// Add --task=<task> option, like `gc`, `commit-graph` or `prefetch`.
+    /// Only the given tasks are run, in order. Without any tasks the
+    /// tasks enabled by config are run, which is `gc` by default.
+    pub fn task(&mut self, task: &str) -> &mut MaintenanceOptions {
+        self.tasks.push(format!("--task={}", task));
+        self
     }
 
-    /// Set tag message.
-    pub fn msg(&mut self, msg: &str) -> &mut TagOptions {
-        self.msg = msg.to_string();
+    /// Add --auto option, only running the tasks which need to run.
+    pub fn auto(&mut self, val: bool) -> &mut MaintenanceOptions {
+        self.auto = val;
         self
     }
+}
 
-    /// Set tagname.
-    pub fn tagname(&mut self, name: &str) -> &mut TagOptions {
-        self.tagname = name.to_string();
-        self
+impl CommandOptions for MaintenanceOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["maintenance", &self.action];
+        push_flag(&mut args, "--auto", self.auto);
+        for t in &self.tasks {
+            args.push(t);
+        }
+        args
     }
 
-    /// Set commit the tag will refer to.
-    pub fn commit(&mut self, commit: &str) -> &mut TagOptions {
-        self.object = commit.to_string();
-        self
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
     }
 
-    /// Set object the tag will refer to.
-    pub fn object(&mut self, object: &str) -> &mut TagOptions {
-        self.object = object.to_string();
-        self
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
     }
 }
 
-impl CommandOptions for TagOptions {
+/// `git notes` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::NotesOptions;
+///
+/// let repo = Repository::new();
+/// NotesOptions::add()
+///     .message("My note")
+///     .object("HEAD")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct NotesOptions {
+    action: String,
+    msg: String,
+    object: String,
+    extra_args: Vec<String>,
+}
+
+impl NotesOptions {
+    /// Create a new set of `git notes add` options.
+    pub fn add() -> NotesOptions {
+        NotesOptions {
+            action: "add".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git notes append` options.
+    pub fn append() -> NotesOptions {
+        NotesOptions {
+            action: "append".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git notes remove` options.
+    pub fn remove() -> NotesOptions {
+        NotesOptions {
+            action: "remove".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Set commit message.
+    pub fn message(&mut self, msg: &str) -> &mut NotesOptions {
+        self.msg = msg.to_owned();
+        self
+    }
+
+    /// Set object to attach notes to.
+    pub fn object(&mut self, object: &str) -> &mut NotesOptions {
+        self.object = object.to_owned();
+        self
+    }
+}
+
+impl CommandOptions for NotesOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["notes", &self.action];
+        if !self.msg.is_empty() {
+            args.push("-m");
+            args.push(&self.msg);
+        }
+        if !self.object.is_empty() {
+            args.push(&self.object);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git pull` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::PullOptions;
+///
+/// let repo = Repository::new();
+/// PullOptions::new()
+///     .remote("origin")
+///     .refspec("master")
+///     .no_edit(true)
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A pull which creates a merge commit opens an editor for the merge message,
+/// unless [`no_edit`] is set. `--no-edit` is not added by default, but
+/// [`Repository::pull`] always uses it.
+///
+/// [`no_edit`]: #method.no_edit
+/// [`Repository::pull`]: ../struct.Repository.html#method.pull
+#[derive(Debug, Default)]
+pub struct PullOptions {
+    allow_unrelated: bool,
+    no_edit: bool,
+    depth: String,
+    repository: String,
+    refspecs: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl PullOptions {
+    /// Create a new set of `git pull` options.
+    pub fn new() -> PullOptions {
+        PullOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Add --allow-unrelated option.
+    pub fn allow_unrelated(&mut self, val: bool) -> &mut PullOptions {
+        self.allow_unrelated = val;
+        self
+    }
+
+    /// Add --no-edit option, to accept the default merge message.
+    pub fn no_edit(&mut self, val: bool) -> &mut PullOptions {
+        self.no_edit = val;
+        self
+    }
+
+    /// Add --depth=<depth> option.
+    pub fn depth(&mut self, depth: u32) -> &mut PullOptions {
+        self.depth = format!("--depth={}", depth);
+        self
+    }
+
+    /// Add refspec to pull command.
+    pub fn refspec(&mut self, file: impl ToString) -> &mut PullOptions {
+        self.refspecs.push(file.to_string());
+        self
+    }
+
+    /// Add multiple refspecs to pull command.
+    pub fn refspecs<I, S>(&mut self, files: I) -> &mut PullOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        for f in files {
+            self.refspecs.push(f.to_string());
+        }
+        self
+    }
+
+    /// Set remote repository source.
+    pub fn remote(&mut self, repo: impl ToString) -> &mut PullOptions {
+        self.repository = repo.to_string();
+        self
+    }
+}
+
+impl CommandOptions for PullOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["pull", "-q"];
+        if self.allow_unrelated {
+            args.push("--allow-unrelated");
+        }
+        if self.no_edit {
+            args.push("--no-edit");
+        }
+        if !self.depth.is_empty() {
+            args.push(&self.depth);
+        }
+        if !self.repository.is_empty() {
+            args.push(&self.repository);
+        }
+        for rs in &self.refspecs {
+            args.push(rs);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git push` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::PushOptions;
+///
+/// let repo = Repository::new();
+/// PushOptions::new()
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With [`porcelain`] the result of each ref is returned, including refs
+/// rejected by the remote:
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::PushOptions;
+///
+/// let repo = Repository::new();
+/// for res in PushOptions::new().porcelain(true).run(&repo)? {
+///     if res.is_rejected() {
+///         println!("{} rejected: {}", res.to, res.summary);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`porcelain`]: #method.porcelain
+#[derive(Debug, Default)]
+pub struct PushOptions {
+    all: bool,
+    tags: bool,
+    force: bool,
+    set_upstream: bool,
+    atomic: bool,
+    porcelain: bool,
+    repository: String,
+    refspecs: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl PushOptions {
+    /// Create a new set of `git tag` options.
+    pub fn new() -> PushOptions {
+        PushOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Add --all option.
+    pub fn all(&mut self, val: bool) -> &mut PushOptions {
+        self.all = val;
+        self
+    }
+
+    /// Add --tags option.
+    pub fn tags(&mut self, val: bool) -> &mut PushOptions {
+        self.tags = val;
+        self
+    }
+
+    /// Add --force option.
+    pub fn force(&mut self, val: bool) -> &mut PushOptions {
+        self.force = val;
+        self
+    }
+
+    /// Add --set-upstream option.
+    pub fn set_upstream(&mut self, val: bool) -> &mut PushOptions {
+        self.set_upstream = val;
+        self
+    }
+
+    /// Add --atomic option. With several refs either all or none of them are
+    /// updated on the remote.
+    pub fn atomic(&mut self, val: bool) -> &mut PushOptions {
+        self.atomic = val;
+        self
+    }
+
+    /// Add --porcelain option, returning the result of each pushed ref.
+    ///
+    /// The command isn't run quietly, since git then omits successful refs.
+    /// If some refs are rejected the results are returned instead of an
+    /// error, check them with [`RefUpdate::is_rejected`].
+    ///
+    /// [`RefUpdate::is_rejected`]: ../struct.RefUpdate.html#method.is_rejected
+    pub fn porcelain(&mut self, val: bool) -> &mut PushOptions {
+        self.porcelain = val;
+        self
+    }
+
+    /// Add refspec to push command.
+    pub fn refspec(&mut self, file: impl ToString) -> &mut PushOptions {
+        self.refspecs.push(file.to_string());
+        self
+    }
+
+    /// Add multiple refspecs to push command.
+    pub fn refspecs<I, S>(&mut self, files: I) -> &mut PushOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        for f in files {
+            self.refspecs.push(f.to_string());
+        }
+        self
+    }
+
+    /// Set remote repository source.
+    pub fn remote(&mut self, repo: impl ToString) -> &mut PushOptions {
+        self.repository = repo.to_string();
+        self
+    }
+}
+
+impl CommandOptions for PushOptions {
+    type Output = Vec<PushResult>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["push"];
+        if self.porcelain {
+            args.push("--porcelain");
+        } else {
+            args.push("-q");
+        }
+        if self.all {
+            args.push("--all");
+        }
+        if self.tags {
+            args.push("--tags");
+        }
+        if self.force {
+            args.push("--force");
+        }
+        if self.set_upstream {
+            args.push("--set-upstream");
+        }
+        if self.atomic {
+            args.push("--atomic");
+        }
+        if !self.repository.is_empty() {
+            args.push(&self.repository);
+        }
+        for rs in &self.refspecs {
+            args.push(rs);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        if !self.porcelain {
+            return Ok(Vec::new());
+        }
+        porcelain::parse_push(out)
+    }
+
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        match repo.run(self.args()) {
+            Ok(out) => self.parse_output(&out),
+            // Rejected refs make git fail, but are reported in the results
+            Err(e) if self.porcelain => {
+                let stdout = match e.downcast_ref::<GitError>() {
+                    Some(err) => err.stdout.clone(),
+                    None => return Err(e),
+                };
+                match self.parse_output(&stdout) {
+                    Ok(results) if results.iter().any(PushResult::is_rejected) => Ok(results),
+                    _ => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Result of a single ref from git push --porcelain, which is a
+/// [`RefUpdate`] like the results of [`FetchOptions::porcelain`].
+///
+/// [`RefUpdate`]: ../struct.RefUpdate.html
+/// [`FetchOptions::porcelain`]: struct.FetchOptions.html#method.porcelain
+pub type PushResult = RefUpdate;
+
+/// `git reflog` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::ReflogOptions;
+///
+/// let repo = Repository::new();
+/// ReflogOptions::expire(true, "30.days.ago").run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Both actions permanently remove reflog entries, which are the only
+/// record of where branches pointed before being reset, rebased or deleted.
+/// Commits only reachable from removed entries can be lost at the next
+/// garbage collection. An expire time of `now` removes all entries.
+#[derive(Debug, Default)]
+pub struct ReflogOptions {
+    action: String,
+    expire: String,
+    all: bool,
+    refs: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl ReflogOptions {
+    /// Create a new set of `git reflog expire --expire=<time>` options,
+    /// pruning entries older than `time`. Expire the reflogs of all refs if
+    /// `all` is true, otherwise only those of each [`reference`].
+    ///
+    /// [`reference`]: #method.reference
+    pub fn expire(all: bool, time: &str) -> ReflogOptions {
+        ReflogOptions {
+            action: "expire".to_string(),
+            expire: format!("--expire={}", time),
+            all,
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git reflog delete <ref@{specifier}>` options,
+    /// deleting a single entry like `HEAD@{2}`.
+    pub fn delete(ref_selector: &str) -> ReflogOptions {
+        ReflogOptions {
+            action: "delete".to_string(),
+            refs: vec![ref_selector.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Add a ref, or entry to delete, to the command.
+    pub fn reference(&mut self, name: &str) -> &mut ReflogOptions {
+        self.refs.push(name.to_string());
+        self
+    }
+}
+
+impl CommandOptions for ReflogOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["reflog", &self.action];
+        if !self.expire.is_empty() {
+            args.push(&self.expire);
+        }
+        if self.all {
+            args.push("--all");
+        }
+        for r in &self.refs {
+            args.push(r);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git remote` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::RemoteOptions;
+///
+/// let repo = Repository::new();
+/// RemoteOptions::add()
+///     .master("master")
+///     .name("upstream")
+///     .url("https://web.com/myrepo.git")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Custom [`fetch_refspec`]s are configured with `git config` after the
+/// remote is added, replacing the default refspec, by every way of running
+/// the command. If configuring them fails the added remote is removed again.
+///
+/// [`fetch_refspec`]: #method.fetch_refspec
+#[derive(Debug, Default)]
+pub struct RemoteOptions {
+    action: String,
+    master: String,
+    tags: Option<bool>,
+    mirror: String,
+    refspecs: Vec<String>,
+    name: String,
+    url: String,
+    branch: String,
+    extra_args: Vec<String>,
+}
+
+impl RemoteOptions {
+    /// Create a new set of `git remote add` options.
+    pub fn add() -> RemoteOptions {
+        RemoteOptions {
+            action: "add".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git remote prune` options.
+    pub fn prune() -> RemoteOptions {
+        RemoteOptions {
+            action: "prune".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git remote set-head` options.
+    ///
+    /// The head is determined automatically with --auto, unless a [`branch`]
+    /// is set.
+    ///
+    /// [`branch`]: #method.branch
+    pub fn set_head() -> RemoteOptions {
+        RemoteOptions {
+            action: "set-head".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add -m <master> option.
+    pub fn master(&mut self, name: &str) -> &mut RemoteOptions {
+        self.master = name.to_string();
+        self
+    }
+
+    /// Add --tags or --no-tags option.
+    pub fn tags(&mut self, val: bool) -> &mut RemoteOptions {
+        self.tags = Some(val);
+        self
+    }
+
+    /// Add --mirror=<mode> option, where mode is `fetch` or `push`.
+    pub fn mirror(&mut self, mode: &str) -> &mut RemoteOptions {
+        self.mirror = format!("--mirror={}", mode);
+        self
+    }
+
+    /// Add a refspec to the remote.<name>.fetch config of an added remote.
+    pub fn fetch_refspec(&mut self, refspec: &str) -> &mut RemoteOptions {
+        self.refspecs.push(refspec.to_string());
+        self
+    }
+
+    // Configure the fetch refspecs of the added remote, removing the remote
+    // if it fails, rather than leaving it half-configured.
+    fn apply_refspecs(&self, repo: &Repository) -> Result<()> {
+        for args in self.refspec_args() {
+            if let Err(e) = repo.run(args.iter().map(String::as_str).collect()) {
+                let _ = repo.run(vec!["remote", "remove", &self.name]);
+                let msg = format!("failed to configure fetch refspecs, removed remote {}", self.name);
+                return Err(e.context(msg).into());
+            }
+        }
+        Ok(())
+    }
+
+    // git config arguments setting the fetch refspecs, if any.
+    fn refspec_args(&self) -> Vec<Vec<String>> {
+        let key = format!("remote.{}.fetch", self.name);
+        self.refspecs
+            .iter()
+            .enumerate()
+            .map(|(i, rs)| {
+                let mode = if i == 0 { "--replace-all" } else { "--add" };
+                vec!["config".to_string(), mode.to_string(), key.clone(), rs.clone()]
+            })
+            .collect()
+    }
+
+    /// Set <name> parameter.
+    pub fn name(&mut self, name: &str) -> &mut RemoteOptions {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set <url> parameter.
+    pub fn url(&mut self, url: &str) -> &mut RemoteOptions {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Set <branch> parameter of set-head.
+    pub fn branch(&mut self, branch: &str) -> &mut RemoteOptions {
+        self.branch = branch.to_string();
+        self
+    }
+}
+
+impl CommandOptions for RemoteOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["remote", &self.action];
+        if !self.master.is_empty() {
+            args.push("-m");
+            args.push(&self.master);
+        }
+        push_flag_pair(&mut args, "--tags", "--no-tags", self.tags);
+        push_arg(&mut args, &self.mirror);
+        args.push(&self.name);
+        if !self.url.is_empty() {
+            args.push(&self.url);
+        }
+        if self.action == "set-head" {
+            if self.branch.is_empty() {
+                args.push("--auto");
+            } else {
+                args.push(&self.branch);
+            }
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        repo.run(self.args())?;
+        self.apply_refspecs(repo)
+    }
+
+    fn run_raw(&self, repo: &Repository) -> Result<String> {
+        let out = repo.run(self.args())?;
+        self.apply_refspecs(repo)?;
+        Ok(out)
+    }
+
+    fn run_full(&self, repo: &Repository) -> Result<CommandOutput> {
+        let out = repo.run_full(self.args())?;
+        if out.success() {
+            self.apply_refspecs(repo)?;
+        }
+        Ok(out)
+    }
+}
+
+/// `git reset` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::ResetOptions;
+///
+/// let repo = Repository::new();
+/// ResetOptions::hard()
+///     .commit("origin/master")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ResetOptions {
+    mode: String,
+    commit: String,
+    extra_args: Vec<String>,
+}
+
+impl ResetOptions {
+    /// Create a new set of `git reset --hard` options.
+    pub fn hard() -> ResetOptions {
+        ResetOptions {
+            mode: "--hard".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git reset --mixed` options.
+    pub fn mixed() -> ResetOptions {
+        ResetOptions {
+            mode: "--mixed".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git reset --soft` options.
+    pub fn soft() -> ResetOptions {
+        ResetOptions {
+            mode: "--soft".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set <commit> to reset to. HEAD is used if not set.
+    pub fn commit(&mut self, rev: &str) -> &mut ResetOptions {
+        self.commit = rev.to_string();
+        self
+    }
+}
+
+impl CommandOptions for ResetOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["reset", "-q", &self.mode];
+        if !self.commit.is_empty() {
+            args.push(&self.commit);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git rev-parse` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::RevParseOptions;
+///
+/// let repo = Repository::new();
+/// let oids = RevParseOptions::new()
+///     .revision("HEAD")
+///     .revision("origin/master")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Returns one line of output per revision, in order. These are full oids,
+/// unless [`short`] or [`abbrev_ref`] is set.
+///
+/// [`short`]: #method.short
+/// [`abbrev_ref`]: #method.abbrev_ref
+#[derive(Debug, Default)]
+pub struct RevParseOptions {
+    short: String,
+    abbrev_ref: bool,
+    verify: bool,
+    revisions: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl RevParseOptions {
+    /// Create a new set of `git rev-parse` options.
+    pub fn new() -> RevParseOptions {
+        RevParseOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Add --short=<n> option, abbreviating oids to at least `n` characters.
+    pub fn short(&mut self, n: usize) -> &mut RevParseOptions {
+        self.short = format!("--short={}", n);
+        self
+    }
+
+    /// Add --abbrev-ref option, giving short ref names like `master` instead
+    /// of oids.
+    pub fn abbrev_ref(&mut self, val: bool) -> &mut RevParseOptions {
+        self.abbrev_ref = val;
+        self
+    }
+
+    /// Add --verify option, failing unless exactly one revision is given and
+    /// it names an existing object.
+    pub fn verify(&mut self, val: bool) -> &mut RevParseOptions {
+        self.verify = val;
+        self
+    }
+
+    /// Add a revision to resolve.
+    pub fn revision(&mut self, rev: &str) -> &mut RevParseOptions {
+        self.revisions.push(rev.to_string());
+        self
+    }
+
+    /// Add multiple revisions to resolve.
+    pub fn revisions<I, S>(&mut self, revs: I) -> &mut RevParseOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        for r in revs {
+            self.revisions.push(r.to_string());
+        }
+        self
+    }
+}
+
+impl CommandOptions for RevParseOptions {
+    type Output = Vec<String>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["rev-parse"];
+        if self.verify {
+            args.push("--verify");
+        }
+        if !self.short.is_empty() {
+            args.push(&self.short);
+        }
+        if self.abbrev_ref {
+            args.push("--abbrev-ref");
+        }
+        for rev in &self.revisions {
+            args.push(rev);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(out.lines().map(|l| l.to_string()).collect())
+    }
+}
+
+/// `git stash` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::StashOptions;
+///
+/// let repo = Repository::new();
+/// StashOptions::push()
+///     .message("Work in progress")
+///     .run(&repo)?;
+/// for entry in StashOptions::list().run(&repo)? {
+///     println!("{}: {}", entry.name, entry.message);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Only listing returns stash entries. If applying a stash with [`pop`] or
+/// [`apply`] results in conflicts a [`ConflictError`] is returned.
+///
+/// [`pop`]: #method.pop
+/// [`apply`]: #method.apply
+/// [`ConflictError`]: ../struct.ConflictError.html
+#[derive(Debug, Default)]
+pub struct StashOptions {
+    action: String,
+    msg: String,
+    entry: String,
+    index: bool,
+    extra_args: Vec<String>,
+}
+
+/// A single entry from git stash list.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StashEntry {
+    /// Position in the stash list, 0 being the newest
+    pub index: usize,
+
+    /// Stash reference, like `stash@{0}`
+    pub name: String,
+
+    /// Branch the stash was created on
+    pub branch: String,
+
+    /// Stash message
+    pub message: String,
+}
+
+impl StashOptions {
+    /// Create a new set of `git stash push` options.
+    pub fn push() -> StashOptions {
+        StashOptions {
+            action: "push".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git stash pop` options.
+    pub fn pop() -> StashOptions {
+        StashOptions {
+            action: "pop".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git stash apply` options.
+    pub fn apply() -> StashOptions {
+        StashOptions {
+            action: "apply".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git stash list` options.
+    pub fn list() -> StashOptions {
+        StashOptions {
+            action: "list".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set stash message of push.
+    pub fn message(&mut self, msg: &str) -> &mut StashOptions {
+        self.msg = msg.to_string();
+        self
+    }
+
+    /// Set stash entry to pop or apply, like `stash@{1}`. The latest is default.
+    pub fn entry(&mut self, entry: &str) -> &mut StashOptions {
+        self.entry = entry.to_string();
+        self
+    }
+
+    /// Add --index option to pop or apply, restoring the staged changes to
+    /// the index as well. Otherwise all changes are left unstaged.
+    pub fn restore_index(&mut self, val: bool) -> &mut StashOptions {
+        self.index = val;
+        self
+    }
+}
+
+impl CommandOptions for StashOptions {
+    type Output = Vec<StashEntry>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["stash", &self.action];
+        if self.action == "list" {
+            args.push("--format=%gd%x00%gs");
+        } else {
+            args.push("-q");
+        }
+        if self.index {
+            args.push("--index");
+        }
+        if !self.msg.is_empty() {
+            args.push("-m");
+            args.push(&self.msg);
+        }
+        if !self.entry.is_empty() {
+            args.push(&self.entry);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        let mut entries = Vec::new();
+        if self.action != "list" {
+            return Ok(entries);
+        }
+        for line in out.lines() {
+            // <name>NUL<subject>
+            let (name, subject) = line
+                .split_once('\0')
+                .ok_or_else(|| format_err!("bad stash list format: {}", line))?;
+            let index = name
+                .strip_prefix("stash@{")
+                .and_then(|n| n.strip_suffix('}'))
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| format_err!("bad stash name: {}", name))?;
+            // Subject is "WIP on <branch>: <commit>" or "On <branch>: <message>"
+            let (branch, message) = match subject
+                .strip_prefix("WIP on ")
+                .or_else(|| subject.strip_prefix("On "))
+                .and_then(|s| s.split_once(": "))
+            {
+                Some((branch, message)) => (branch, message),
+                None => ("", subject),
+            };
+            entries.push(StashEntry {
+                index,
+                name: name.to_string(),
+                branch: branch.to_string(),
+                message: message.to_string(),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        let out = repo.run(self.args()).map_err(conflict_error)?;
+        self.parse_output(&out)
+    }
+}
+
+/// `git status` command.
+///
+/// The status is always read in porcelain v2 format, and parsed into a
+/// [`Status`]. Pathspecs can be given to limit the status to parts of the
+/// repository.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::StatusOptions;
+///
+/// let repo = Repository::new();
+/// let status = StatusOptions::new()
+///     .pathspec("src")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Status`]: ../struct.Status.html
+#[derive(Debug, Default)]
+pub struct StatusOptions {
+    pathspecs: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl StatusOptions {
+    /// Create a new set of `git status` options.
+    pub fn new() -> StatusOptions {
+        StatusOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Add a pathspec to status command.
+    pub fn pathspec(&mut self, pathspec: impl ToString) -> &mut StatusOptions {
+        self.pathspecs.push(pathspec.to_string());
+        self
+    }
+
+    /// Add multiple pathspecs to status command.
+    pub fn pathspecs<I, S>(&mut self, pathspecs: I) -> &mut StatusOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        for p in pathspecs {
+            self.pathspecs.push(p.to_string());
+        }
+        self
+    }
+}
+
+impl CommandOptions for StatusOptions {
+    type Output = Status;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["status", "--porcelain=v2", "--branch", "--ignored"];
+        if !self.pathspecs.is_empty() {
+            args.push("--");
+            for p in &self.pathspecs {
+                args.push(p);
+            }
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Status::try_from(out)
+    }
+}
+
+/// `git submodule` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::SubmoduleOptions;
+///
+/// let repo = Repository::new();
+/// SubmoduleOptions::update()
+///     .init(true)
+///     .recursive(true)
+///     .jobs(8)
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct SubmoduleOptions {
+    action: String,
+    init: bool,
+    recursive: bool,
+    // Complete --jobs=<n> argument
+    jobs: String,
+    paths: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl SubmoduleOptions {
+    /// Create a new set of `git submodule update` options.
+    pub fn update() -> SubmoduleOptions {
+        SubmoduleOptions {
+            action: "update".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add --init option, initializing submodules which aren't yet.
+    pub fn init(&mut self, val: bool) -> &mut SubmoduleOptions {
+        self.init = val;
+        self
+    }
+
+    /// Add --recursive option, updating nested submodules as well.
+    pub fn recursive(&mut self, val: bool) -> &mut SubmoduleOptions {
+        self.recursive = val;
+        self
+    }
+
+    /// Add --jobs=<n> option, cloning up to `n` submodules in parallel.
+    pub fn jobs(&mut self, n: u32) -> &mut SubmoduleOptions {
+        self.jobs = format!("--jobs={}", n);
+        self
+    }
+
+    /// Add a path, limiting the command to the submodules at matching paths.
+    pub fn path(&mut self, path: impl ToString) -> &mut SubmoduleOptions {
+        self.paths.push(path.to_string());
+        self
+    }
+}
+
+impl CommandOptions for SubmoduleOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["submodule", "--quiet", &self.action];
+        push_flag(&mut args, "--init", self.init);
+        push_flag(&mut args, "--recursive", self.recursive);
+        push_arg(&mut args, &self.jobs);
+        args.push("--");
+        for p in &self.paths {
+            args.push(p);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// `git tag` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::TagOptions;
+///
+/// let repo = Repository::new();
+/// TagOptions::add()
+///     .msg("A new tag")
+///     .tagname("v0.0")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// To list tags use [`TagListOptions`], and to verify the signature of a tag
+/// use [`TagVerifyOptions`].
+///
+/// Adding a tag requires either a [`msg`] for an annotated tag, or
+/// [`lightweight`]. Otherwise git would open an editor for the message, so
+/// [`validate`] fails before git is run.
+///
+/// [`TagListOptions`]: struct.TagListOptions.html
+/// [`TagVerifyOptions`]: struct.TagVerifyOptions.html
+/// [`msg`]: #method.msg
+/// [`lightweight`]: #method.lightweight
+/// [`validate`]: #method.validate
+#[derive(Debug, Default)]
+pub struct TagOptions {
+    action: String,
+    msg: String,
+    lightweight: bool,
+    tagname: String,
+    // commit/object
+    object: String,
+    config: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl TagOptions {
+    /// Create a new set of `git tag` options.
+    pub fn add() -> TagOptions {
+        TagOptions {
+            action: "add".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git tag -d` options.
+    pub fn delete() -> TagOptions {
+        TagOptions {
+            action: "delete".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git tag -v` options, see [`TagVerifyOptions`].
+    ///
+    /// [`TagVerifyOptions`]: struct.TagVerifyOptions.html
+    pub fn verify() -> TagVerifyOptions {
+        TagVerifyOptions::new()
+    }
+
+    /// Set tag message.
+    pub fn msg(&mut self, msg: &str) -> &mut TagOptions {
+        self.msg = msg.to_string();
+        self
+    }
+
+    /// Create a lightweight tag, which is a plain ref without a message,
+    /// with `--no-sign` so `tag.gpgSign` can't turn it into an annotated tag.
+    pub fn lightweight(&mut self, val: bool) -> &mut TagOptions {
+        self.lightweight = val;
+        self
+    }
+
+    /// Set tagname.
+    pub fn tagname(&mut self, name: &str) -> &mut TagOptions {
+        self.tagname = name.to_string();
+        self
+    }
+
+    /// Set commit the tag will refer to.
+    pub fn commit(&mut self, commit: &str) -> &mut TagOptions {
+        self.object = commit.to_string();
+        self
+    }
+
+    /// Set object the tag will refer to.
+    pub fn object(&mut self, object: &str) -> &mut TagOptions {
+        self.object = object.to_string();
+        self
+    }
+
+    /// Set the program used for signing, with a `-c gpg.program=<program>`
+    /// override for this command only.
+    pub fn gpg_program(&mut self, program: &str) -> &mut TagOptions {
+        set_config(&mut self.config, "gpg.program", program);
+        self
+    }
+
+    /// Set the signature format, `openpgp`, `x509` or `ssh`, with a
+    /// `-c gpg.format=<format>` override for this command only.
+    pub fn gpg_format(&mut self, format: &str) -> &mut TagOptions {
+        set_config(&mut self.config, "gpg.format", format);
+        self
+    }
+
+    /// Check that the tag can be added without opening an editor, which is
+    /// what [`run`] does before running git.
+    ///
+    /// Fails if neither a message nor [`lightweight`] is set, if both are
+    /// set, or if the extra arguments contain `-e` or `--edit`.
+    ///
+    /// [`run`]: #method.run
+    /// [`lightweight`]: #method.lightweight
+    pub fn validate(&self) -> Result<()> {
+        if self.action != "add" {
+            return Ok(());
+        }
+        if self.extra_args.iter().any(|a| a == "-e" || a == "--edit") {
+            bail!("git tag would open an editor, -e and --edit are not allowed");
+        }
+        let message_arg = self.extra_args.iter().any(|a| {
+            ["-m", "-F", "--message", "--file"]
+                .iter()
+                .any(|opt| a.starts_with(opt))
+        });
+        let has_msg = !self.msg.is_empty() || message_arg;
+        if self.lightweight && has_msg {
+            bail!("git tag can't add a lightweight tag with a message");
+        }
+        if !self.lightweight && !has_msg {
+            bail!("git tag would open an editor, set a message or create a lightweight tag");
+        }
+        Ok(())
+    }
+}
+
+impl CommandOptions for TagOptions {
+    type Output = ();
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["tag"];
+        if self.action == "delete" {
+            args.push("-d");
+        }
+        push_flag(&mut args, "--no-sign", self.lightweight);
+        if !self.msg.is_empty() {
+            args.push("-m");
+            args.push(&self.msg);
+        }
+        args.push(&self.tagname);
+        if !self.object.is_empty() {
+            args.push(&self.object);
+        }
+        args
+    }
+
+    #[inline]
+    fn config(&self) -> &[String] {
+        &self.config
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(())
+    }
+
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        self.validate()?;
+        let out = repo.run(self.args())?;
+        self.parse_output(&out)
+    }
+}
+
+/// `git tag -v` command, verifying the GPG signature of a tag.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::TagVerifyOptions;
+///
+/// let repo = Repository::new();
+/// if !TagVerifyOptions::new().tagname("v1.0").run(&repo)? {
+///     println!("v1.0 isn't signed by a trusted key");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Running the command returns `true` if the signature is good, and `false`
+/// if the tag exists but its signature is missing or invalid. A missing tag
+/// is an error, checked before git is run by [`run`], [`run_raw`] and
+/// [`run_full`].
+///
+/// [`run`]: #method.run
+/// [`run_raw`]: #method.run_raw
+/// [`run_full`]: #method.run_full
+#[derive(Debug, Default)]
+pub struct TagVerifyOptions {
+    tagname: String,
+    config: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl TagVerifyOptions {
+    /// Create a new set of `git tag -v` options.
+    pub fn new() -> TagVerifyOptions {
+        TagVerifyOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Set tagname.
+    pub fn tagname(&mut self, name: &str) -> &mut TagVerifyOptions {
+        self.tagname = name.to_string();
+        self
+    }
+
+    /// Set the program used for verifying, with a `-c gpg.program=<program>`
+    /// override for this command only.
+    pub fn gpg_program(&mut self, program: &str) -> &mut TagVerifyOptions {
+        set_config(&mut self.config, "gpg.program", program);
+        self
+    }
+
+    /// Set the signature format, `openpgp`, `x509` or `ssh`, with a
+    /// `-c gpg.format=<format>` override for this command only.
+    pub fn gpg_format(&mut self, format: &str) -> &mut TagVerifyOptions {
+        set_config(&mut self.config, "gpg.format", format);
+        self
+    }
+
+    // Git exits with 1 both if the signature is bad and if the tag doesn't
+    // exist, and only tells them apart on stderr.
+    fn check_tag(&self, repo: &Repository) -> Result<()> {
+        if !repo.tag_exists(&self.tagname)? {
+            bail!("can't verify tag {}, no such tag", self.tagname);
+        }
+        Ok(())
+    }
+}
+
+impl CommandOptions for TagVerifyOptions {
+    type Output = bool;
+
+    fn git_args(&self) -> Vec<&str> {
+        vec!["tag", "-v", &self.tagname]
+    }
+
+    #[inline]
+    fn config(&self) -> &[String] {
+        &self.config
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(true)
+    }
+
+    fn run(&self, repo: &Repository) -> Result<Self::Output> {
+        self.check_tag(repo)?;
+        match repo.run(self.args()) {
+            Ok(_) => Ok(true),
+            Err(e) if is_exit_one(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn run_raw(&self, repo: &Repository) -> Result<String> {
+        self.check_tag(repo)?;
+        repo.run(self.args())
+    }
+
+    fn run_full(&self, repo: &Repository) -> Result<CommandOutput> {
+        self.check_tag(repo)?;
+        repo.run_full(self.args())
+    }
+}
+
+/// `git tag --list` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::TagListOptions;
+///
+/// let repo = Repository::new();
+/// let releases = TagListOptions::new()
+///     .pattern("v*")
+///     .merged("master")
+///     .sort("-version:refname")
+///     .run(&repo)?;
+/// if let Some(latest) = releases.first() {
+///     println!("latest release: {}", latest);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Returns the tag names, in the order listed by git.
+#[derive(Debug, Default)]
+pub struct TagListOptions {
+    sort: String,
+    merged: String,
+    points_at: String,
+    patterns: Vec<String>,
+    extra_args: Vec<String>,
+}
+
+impl TagListOptions {
+    /// Create a new set of `git tag --list` options.
+    pub fn new() -> TagListOptions {
+        TagListOptions {
+            ..Default::default()
+        }
+    }
+
+    /// Add --sort=<key> option, like `-version:refname` for the newest
+    /// version first or `creatordate`.
+    pub fn sort(&mut self, key: &str) -> &mut TagListOptions {
+        self.sort = format!("--sort={}", key);
+        self
+    }
+
+    /// Add --merged <commit> option, listing only tags reachable from the commit.
+    pub fn merged(&mut self, commit: &str) -> &mut TagListOptions {
+        self.merged = commit.to_string();
+        self
+    }
+
+    /// Add --points-at <object> option, listing only tags of the object.
+    pub fn points_at(&mut self, object: &str) -> &mut TagListOptions {
+        self.points_at = object.to_string();
+        self
+    }
+
+    /// Add a shell wildcard pattern, like `v1.*`, listing only matching tags.
+    pub fn pattern(&mut self, pattern: &str) -> &mut TagListOptions {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+}
+
+impl CommandOptions for TagListOptions {
+    type Output = Vec<String>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["tag", "--list"];
+        if !self.sort.is_empty() {
+            args.push(&self.sort);
+        }
+        if !self.merged.is_empty() {
+            args.push("--merged");
+            args.push(&self.merged);
+        }
+        if !self.points_at.is_empty() {
+            args.push("--points-at");
+            args.push(&self.points_at);
+        }
+        for p in &self.patterns {
+            args.push(p);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    #[inline]
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        Ok(out.lines().map(|l| l.to_string()).collect())
+    }
+}
+
+/// `git update-ref` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::UpdateRefOptions;
+///
+/// let repo = Repository::new();
+/// UpdateRefOptions::update("refs/heads/master", "1a2b3c", "4d5e6f")
+///     .run(&repo)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct UpdateRefOptions {
+    action: String,
+    refname: String,
+    new_oid: String,
+    old_oid: String,
+    extra_args: Vec<String>,
+}
+
+impl UpdateRefOptions {
+    /// Create a new set of `git update-ref` options, creating `refname`
+    /// pointing at `new_oid`.
+    ///
+    /// Fails if the ref already exists.
+    pub fn create(refname: &str, new_oid: &str) -> UpdateRefOptions {
+        UpdateRefOptions {
+            action: "create".to_string(),
+            refname: refname.to_string(),
+            new_oid: new_oid.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git update-ref -d` options, deleting `refname`.
+    pub fn delete(refname: &str) -> UpdateRefOptions {
+        UpdateRefOptions {
+            action: "delete".to_string(),
+            refname: refname.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git update-ref` options, moving `refname` to
+    /// `new_oid`.
+    ///
+    /// Fails if `refname` doesn't currently point at `old_oid`, so concurrent
+    /// updates of the ref can't be lost.
+    pub fn update(refname: &str, new_oid: &str, old_oid: &str) -> UpdateRefOptions {
+        UpdateRefOptions {
+            action: "update".to_string(),
+            refname: refname.to_string(),
+            new_oid: new_oid.to_string(),
+            old_oid: old_oid.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl CommandOptions for UpdateRefOptions {
     type Output = ();
 
     fn git_args(&self) -> Vec<&str> {
-        let mut args = vec!["tag"];
+        let mut args = vec!["update-ref"];
         if self.action == "delete" {
             args.push("-d");
         }
-        if !self.msg.is_empty() {
-            args.push("-m");
-            args.push(&self.msg);
+        args.push(&self.refname);
+        if !self.new_oid.is_empty() {
+            args.push(&self.new_oid);
         }
-        args.push(&self.tagname);
-        if !self.object.is_empty() {
-            args.push(&self.object);
+        if self.action == "create" {
+            // An empty old value makes sure the ref doesn't exist
+            args.push("");
+        } else if !self.old_oid.is_empty() {
+            args.push(&self.old_oid);
         }
         args
     }
 
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
     #[inline]
     fn parse_output(&self, out: &str) -> Result<Self::Output> {
         Ok(())
     }
 }
 
+/// `git worktree` command.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::WorktreeOptions;
+///
+/// let repo = Repository::new();
+/// WorktreeOptions::add("../hotfix")
+///     .commit_ish("master")
+///     .run(&repo)?;
+/// for wt in WorktreeOptions::list().run(&repo)? {
+///     println!("{} {:?}", wt.path.display(), wt.branch);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Only listing returns worktrees.
+#[derive(Debug, Default)]
+pub struct WorktreeOptions {
+    action: String,
+    path: String,
+    commit_ish: String,
+    extra_args: Vec<String>,
+}
+
+/// A single worktree from git worktree list.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Worktree {
+    /// Path of the worktree
+    pub path: PathBuf,
+
+    /// Object id (oid) of the checked out commit, empty for bare repositories
+    pub head: String,
+
+    /// Checked out branch, without `refs/heads/`, if not detached
+    pub branch: Option<String>,
+
+    /// True for the main worktree of a bare repository
+    pub bare: bool,
+
+    /// True if HEAD is detached
+    pub detached: bool,
+
+    /// True if the worktree is locked
+    pub locked: bool,
+
+    /// True if the worktree can be pruned
+    pub prunable: bool,
+}
+
+impl WorktreeOptions {
+    /// Create a new set of `git worktree add` options, adding a worktree at
+    /// `path`.
+    pub fn add(path: &str) -> WorktreeOptions {
+        WorktreeOptions {
+            action: "add".to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new set of `git worktree list` options.
+    pub fn list() -> WorktreeOptions {
+        WorktreeOptions {
+            action: "list".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set branch or commit to check out in the added worktree.
+    pub fn commit_ish(&mut self, commit_ish: &str) -> &mut WorktreeOptions {
+        self.commit_ish = commit_ish.to_string();
+        self
+    }
+}
+
+impl CommandOptions for WorktreeOptions {
+    type Output = Vec<Worktree>;
+
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["worktree", &self.action];
+        if self.action == "list" {
+            args.push("--porcelain");
+        } else {
+            args.push("-q");
+        }
+        if !self.path.is_empty() {
+            args.push(&self.path);
+        }
+        if !self.commit_ish.is_empty() {
+            args.push(&self.commit_ish);
+        }
+        args
+    }
+
+    #[inline]
+    fn extra(&self) -> &[String] {
+        &self.extra_args
+    }
+
+    fn parse_output(&self, out: &str) -> Result<Self::Output> {
+        let mut worktrees = Vec::new();
+        if self.action != "list" {
+            return Ok(worktrees);
+        }
+        // Worktrees are separated by empty lines, starting with the path
+        let mut wt: Option<Worktree> = None;
+        for line in out.lines() {
+            let (key, val) = line.split_once(' ').unwrap_or((line, ""));
+            if key == "worktree" {
+                worktrees.extend(wt.take());
+                wt = Some(Worktree {
+                    path: PathBuf::from(val),
+                    ..Default::default()
+                });
+                continue;
+            }
+            let wt = match (wt.as_mut(), line.is_empty()) {
+                (_, true) => continue,
+                (Some(wt), false) => wt,
+                (None, false) => bail!("bad worktree list format: {}", line),
+            };
+            match key {
+                "HEAD" => wt.head = val.to_string(),
+                "branch" => {
+                    wt.branch = Some(val.strip_prefix("refs/heads/").unwrap_or(val).to_string())
+                }
+                "bare" => wt.bare = true,
+                "detached" => wt.detached = true,
+                "locked" => wt.locked = true,
+                "prunable" => wt.prunable = true,
+                _ => (),
+            }
+        }
+        worktrees.extend(wt);
+        Ok(worktrees)
+    }
+}
+
+// Set `key` to `val` in the config overrides, replacing any earlier value.
+fn set_config(config: &mut Vec<String>, key: &str, val: &str) {
+    let prefix = format!("{}=", key);
+    config.retain(|c| !c.starts_with(&prefix));
+    config.push(format!("{}={}", key, val));
+}
+
+// Push `flag` to the arguments if `val` is true.
+fn push_flag<'a>(args: &mut Vec<&'a str>, flag: &'a str, val: bool) {
+    if val {
+        args.push(flag);
+    }
+}
+
+// Push the `yes` or `no` flag to the arguments if `val` is set, like
+// `--tags`/`--no-tags`.
+fn push_flag_pair<'a>(args: &mut Vec<&'a str>, yes: &'a str, no: &'a str, val: Option<bool>) {
+    match val {
+        Some(true) => args.push(yes),
+        Some(false) => args.push(no),
+        None => (),
+    }
+}
+
+// Push a complete argument, like `--date=<date>`, if it's set.
+fn push_arg<'a>(args: &mut Vec<&'a str>, arg: &'a str) {
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+}
+
+// Implement the extra_args builder method for options types with an
+// extra_args field.
+macro_rules! impl_extra_args {
+    ($($options:ty),* $(,)?) => {$(
+        impl $options {
+            /// Add extra arguments to the command, for options without a builder method.
+            pub fn extra_args(&mut self, args: &[&str]) -> &mut $options {
+                self.extra_args.extend(args.iter().map(|a| a.to_string()));
+                self
+            }
+        }
+    )*};
+}
+
+impl_extra_args!(
+    AddOptions,
+    BranchOptions,
+    CloneOptions,
+    CommitOptions,
+    CommitGraphOptions,
+    DiffOptions,
+    FetchOptions,
+    LogOptions,
+    MaintenanceOptions,
+    NotesOptions,
+    PullOptions,
+    PushOptions,
+    ReflogOptions,
+    RemoteOptions,
+    ResetOptions,
+    RevParseOptions,
+    StashOptions,
+    StatusOptions,
+    SubmoduleOptions,
+    TagOptions,
+    TagVerifyOptions,
+    TagListOptions,
+    UpdateRefOptions,
+    WorktreeOptions,
+);
+
+// Parse --name-status -z output into the changed files.
+pub(crate) fn parse_name_status(out: &str) -> Result<Vec<DiffFile>> {
+    // <status>[<score>] NUL <path> NUL, with an extra path for renames
+    // and copies: <status><score> NUL <orig path> NUL <path> NUL
+    //
+    // Combined diffs of merges have a status letter per parent and a single
+    // path, which is reported with the status of the first parent.
+    let mut files = Vec::new();
+    let mut fields = out.split_terminator('\0');
+    while let Some(code) = fields.next() {
+        let mut chars = code.chars();
+        let status = match chars.next() {
+            Some(c) => c,
+            None => bail!("bad name-status format: empty status"),
+        };
+        let combined = code.len() > 1 && code.chars().all(|c| c.is_ascii_uppercase());
+        let score = match chars.as_str() {
+            "" => 0,
+            _ if combined => 0,
+            n => n.parse::<u8>().with_context(|_| format!("bad name-status score: {}", code))?,
+        };
+        let first = match fields.next() {
+            Some(path) => path.to_string(),
+            None => bail!("bad name-status format: missing path after {}", code),
+        };
+        let file = if !combined && (status == 'R' || status == 'C') {
+            match fields.next() {
+                Some(path) => DiffFile { status, score, path: path.to_string(), orig_path: Some(first) },
+                None => bail!("bad name-status format: missing new path of {}", first),
+            }
+        } else {
+            DiffFile { status, score, path: first, orig_path: None }
+        };
+        files.push(file);
+    }
+    Ok(files)
+}
+
 /*******************************************************************************
  *                                                                             *
  * Test
@@ -757,29 +3468,409 @@ mod tests {
     use super::*;
 
     #[test]
-    fn add() {
-        assert_eq!(AddOptions::new().git_args(), vec!["add"]);
+    fn push_flags() {
+        let mut args = vec!["cmd"];
+        push_flag(&mut args, "--yes", true);
+        push_flag(&mut args, "--no", false);
+        push_flag_pair(&mut args, "--tags", "--no-tags", Some(false));
+        push_flag_pair(&mut args, "--all", "--no-all", None);
+        push_arg(&mut args, "--date=now");
+        push_arg(&mut args, "");
+        assert_eq!(args, vec!["cmd", "--yes", "--no-tags", "--date=now"]);
+    }
+
+    #[test]
+    fn add() {
+        assert_eq!(AddOptions::new().git_args(), vec!["add"]);
+        assert_eq!(
+            AddOptions::new()
+                .all(false)
+                .chmod(false)
+                .pathspec("foobar")
+                .git_args(),
+            vec!["add", "--no-all", "--chmod=-x", "foobar"]
+        );
+        assert_eq!(
+            AddOptions::new()
+                .all(true)
+                .chmod(true)
+                .pathspecs(["foo", "bar"])
+                .git_args(),
+            vec!["add", "--all", "--chmod=+x", "foo", "bar"]
+        );
+        assert_eq!(
+            AddOptions::new()
+                .pathspecs(["foo", "bar"])
+                .pathspec_from_file("paths.txt")
+                .git_args(),
+            vec!["add", "--pathspec-from-file", "paths.txt"]
+        );
+        assert_eq!(
+            AddOptions::new()
+                .pathspec_from_file("paths.txt")
+                .pathspec_file_nul(true)
+                .git_args(),
+            vec!["add", "--pathspec-from-file", "paths.txt", "--pathspec-file-nul"]
+        );
+    }
+
+    #[test]
+    fn branch() {
+        assert_eq!(
+            BranchOptions::list().git_args(),
+            vec!["branch", "--list", "--format=%(refname)"]
+        );
+        assert_eq!(
+            BranchOptions::list().contains("HEAD~1").git_args(),
+            vec!["branch", "--list", "--format=%(refname)", "--contains", "HEAD~1"]
+        );
+        assert_eq!(
+            BranchOptions::list().merged("master").git_args(),
+            vec!["branch", "--list", "--format=%(refname)", "--merged", "master"]
+        );
+        assert_eq!(
+            BranchOptions::list().no_merged("master").git_args(),
+            vec!["branch", "--list", "--format=%(refname)", "--no-merged", "master"]
+        );
+        assert_eq!(
+            BranchOptions::list()
+                .parse_output("(HEAD detached at 1a2b3c4)\nrefs/heads/feature/foo\nrefs/heads/master\nrefs/remotes/origin/master\n")
+                .unwrap(),
+            vec!["feature/foo", "master", "origin/master"]
+        );
+        assert_eq!(
+            BranchOptions::set_upstream_to("origin/main").git_args(),
+            vec!["branch", "--set-upstream-to=origin/main"]
+        );
+        assert_eq!(
+            BranchOptions::set_upstream_to("origin/main").branch("feature").git_args(),
+            vec!["branch", "--set-upstream-to=origin/main", "feature"]
+        );
+        assert_eq!(
+            BranchOptions::unset_upstream().git_args(),
+            vec!["branch", "--unset-upstream"]
+        );
+        assert_eq!(
+            BranchOptions::unset_upstream().branch("feature").git_args(),
+            vec!["branch", "--unset-upstream", "feature"]
+        );
+    }
+
+    #[test]
+    fn gpg_config() {
+        let mut opts = CommitOptions::new();
+        opts.message("Signed")
+            .gpg_program("gpg2")
+            .gpg_format("ssh")
+            .extra_args(&["-S"]);
+        assert_eq!(opts.git_args(), vec!["commit", "-q", "-m", "Signed"]);
+        assert_eq!(
+            opts.args(),
+            vec![
+                "-c",
+                "gpg.program=gpg2",
+                "-c",
+                "gpg.format=ssh",
+                "commit",
+                "-q",
+                "-m",
+                "Signed",
+                "-S"
+            ]
+        );
+
+        // Setting a value again replaces it
+        opts.gpg_program("/usr/bin/gpg");
+        assert_eq!(opts.config(), ["gpg.format=ssh", "gpg.program=/usr/bin/gpg"]);
+    }
+
+    #[test]
+    fn clone() {
+        assert_eq!(
+            CloneOptions::new().clone_args("https://web.com/repo.git"),
+            vec!["clone", "https://web.com/repo.git"]
+        );
+        assert_eq!(
+            CloneOptions::new()
+                .branch("dev")
+                .recurse_submodules(true)
+                .dir("repo")
+                .extra_args(&["--depth=1"])
+                .clone_args("https://web.com/repo.git"),
+            vec!["clone", "--branch", "dev", "--recurse-submodules", "--depth=1", "https://web.com/repo.git", "repo"]
+        );
+        assert_eq!(
+            CloneOptions::new()
+                .recurse_submodules(true)
+                .recurse_submodules_path("libs/a")
+                .recurse_submodules_path("libs/b")
+                .clone_args("url"),
+            vec!["clone", "--recurse-submodules=libs/a", "--recurse-submodules=libs/b", "url"]
+        );
+        assert_eq!(
+            CloneOptions::new()
+                .recurse_submodules(true)
+                .recurse_submodules(false)
+                .clone_args("url"),
+            vec!["clone", "url"]
+        );
+        assert_eq!(
+            CloneOptions::new().recurse_submodules(true).jobs(4).clone_args("url"),
+            vec!["clone", "--recurse-submodules", "--jobs=4", "url"]
+        );
+        assert_eq!(
+            CloneOptions::new().filter("blob:none").dir("repo").clone_args("url"),
+            vec!["clone", "--filter=blob:none", "url", "repo"]
+        );
+    }
+
+    #[test]
+    fn fetch() {
+        assert_eq!(FetchOptions::new().git_args(), vec!["fetch", "-q"]);
+        assert_eq!(
+            FetchOptions::new().all(true).prune(true).jobs(8).git_args(),
+            vec!["fetch", "-q", "--all", "--prune", "--jobs=8"]
+        );
+        assert_eq!(
+            FetchOptions::new()
+                .remote("origin")
+                .refspec("main")
+                .refspec("dev")
+                .git_args(),
+            vec!["fetch", "-q", "origin", "main", "dev"]
+        );
+        assert_eq!(
+            FetchOptions::new().filter("tree:0").remote("origin").git_args(),
+            vec!["fetch", "-q", "--filter=tree:0", "origin"]
+        );
+        assert_eq!(
+            FetchOptions::new().porcelain(true).remote("origin").git_args(),
+            vec!["fetch", "--porcelain", "origin"]
+        );
+        let out = "* 0000000000000000000000000000000000000000 d43fb9b2bd1ad0b6c2f5bbcd4a2a4f6ca1b46b26 refs/remotes/origin/dev\n";
+        let updates = FetchOptions::new().porcelain(true).parse_output(out).unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].to, "refs/remotes/origin/dev");
+        assert!(FetchOptions::new().parse_output(out).unwrap().is_empty());
+    }
+
+    #[test]
+    fn commit_date() {
+        assert_eq!(
+            CommitOptions::new().message("m").date("2020-06-13T12:00:00+02:00").git_args(),
+            vec!["commit", "-q", "-m", "m", "--date=2020-06-13T12:00:00+02:00"]
+        );
+
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut opts = CommitOptions::new();
+        opts.message("m").date_now();
+        let args = opts.git_args();
+        let date = args[4].strip_prefix("--date=@").expect("not a --date=@<seconds> argument");
+        let (secs, tz) = date.split_once(' ').expect("missing time zone");
+        let secs: u64 = secs.parse().expect("seconds not a number");
+        assert!(secs >= before && secs <= before + 5, "{} is not the current time", secs);
+        assert_eq!(tz, "+0000");
+    }
+
+    #[test]
+    fn commit_needs_editor() {
+        let repo = Repository::new();
+        let err = CommitOptions::new().run(&repo).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "git commit would open an editor, set a message or use no_edit when amending"
+        );
+        assert!(CommitOptions::new().amend(true).needs_editor());
+        assert!(CommitOptions::new().squash("HEAD").needs_editor());
+        assert!(CommitOptions::new().reedit_message("HEAD").needs_editor());
+
+        assert!(!CommitOptions::new().message("msg").needs_editor());
+        assert!(!CommitOptions::new().allow_empty_message(true).needs_editor());
+        assert!(!CommitOptions::new().amend(true).no_edit(true).needs_editor());
+        assert!(!CommitOptions::new().reuse_message("HEAD").needs_editor());
+        assert!(!CommitOptions::new().fixup("HEAD").needs_editor());
+        assert!(!CommitOptions::new().extra_args(&["-F", "msg.txt"]).needs_editor());
+        assert!(!CommitOptions::new()
+            .reedit_message("HEAD")
+            .extra_args(&["--no-edit"])
+            .needs_editor());
+        assert!(!CommitOptions::new().reedit_message("HEAD").no_edit(true).needs_editor());
+    }
+
+    #[test]
+    fn commit_validate() {
+        let err = CommitOptions::new().all(true).validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "git commit would open an editor, set a message or use no_edit when amending"
+        );
+        let err = CommitOptions::new()
+            .message("msg")
+            .extra_args(&["--edit"])
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "git commit would open an editor, -e and --edit are not allowed");
+        assert!(CommitOptions::new().amend(true).no_edit(true).extra_args(&["-e"]).validate().is_err());
+        assert!(CommitOptions::new().reedit_message("HEAD").validate().is_err());
+
+        CommitOptions::new().message("msg").all(true).validate().unwrap();
+        CommitOptions::new().amend(true).no_edit(true).validate().unwrap();
+        CommitOptions::new().fixup("HEAD~2").validate().unwrap();
+    }
+
+    #[test]
+    fn commit_graph() {
+        assert_eq!(
+            CommitGraphOptions::write(true).git_args(),
+            vec!["commit-graph", "write", "--reachable"]
+        );
+        assert_eq!(CommitGraphOptions::write(false).git_args(), vec!["commit-graph", "write"]);
+        assert_eq!(CommitGraphOptions::verify().git_args(), vec!["commit-graph", "verify"]);
+    }
+
+    #[test]
+    fn diff() {
+        assert_eq!(DiffOptions::new().git_args(), vec!["diff", "--"]);
+        assert_eq!(
+            DiffOptions::new()
+                .cached(true)
+                .word_diff(true)
+                .color(ColorMode::Always)
+                .against("HEAD~1")
+                .pathspec("src")
+                .git_args(),
+            vec!["diff", "--cached", "--word-diff", "--color=always", "HEAD~1", "--", "src"]
+        );
+        assert_eq!(
+            DiffOptions::new()
+                .color(ColorMode::Never)
+                .against("v1.0")
+                .against("v2.0")
+                .extra_args(&["--stat"])
+                .args(),
+            vec!["diff", "--color=never", "v1.0", "v2.0", "--stat", "--"]
+        );
+        assert_eq!(
+            DiffOptions::new().color(ColorMode::Auto).git_args(),
+            vec!["diff", "--color=auto", "--"]
+        );
+        assert_eq!(
+            DiffOptions::new().name_status(true).find_renames(Some(50)).git_args(),
+            vec!["diff", "--name-status", "-z", "-M50%", "--"]
+        );
+        assert_eq!(
+            DiffOptions::new().find_renames(None).find_copies(Some(75)).git_args(),
+            vec!["diff", "-M", "-C75%", "--"]
+        );
+        assert_eq!(DiffOptions::new().find_copies(None).git_args(), vec!["diff", "-C", "--"]);
+    }
+
+    #[test]
+    fn diff_merge_base() {
+        assert_eq!(
+            DiffOptions::new()
+                .merge_base(true)
+                .against("main")
+                .against("feature")
+                .pathspec("src")
+                .git_args(),
+            vec!["diff", "main...feature", "--", "src"]
+        );
+        assert_eq!(
+            DiffOptions::new().against("main").merge_base(true).git_args(),
+            vec!["diff", "main...HEAD", "--"]
+        );
+        assert_eq!(DiffOptions::new().merge_base(true).git_args(), vec!["diff", "--"]);
+        assert_eq!(
+            DiffOptions::new()
+                .against("main")
+                .against("feature")
+                .merge_base(true)
+                .merge_base(false)
+                .git_args(),
+            vec!["diff", "main", "feature", "--"]
+        );
+    }
+
+    #[test]
+    fn diff_name_status() {
+        let out = "M\0src/lib.rs\0R087\0old name.rs\0new name.rs\0C100\0a.txt\0b.txt\0D\0gone.txt\0";
+        let diff = DiffOptions::new().name_status(true).parse_output(out).unwrap();
+        assert_eq!(diff.patch, "");
+        assert_eq!(
+            diff.files,
+            vec![
+                DiffFile { status: 'M', score: 0, path: "src/lib.rs".to_string(), orig_path: None },
+                DiffFile {
+                    status: 'R',
+                    score: 87,
+                    path: "new name.rs".to_string(),
+                    orig_path: Some("old name.rs".to_string()),
+                },
+                DiffFile {
+                    status: 'C',
+                    score: 100,
+                    path: "b.txt".to_string(),
+                    orig_path: Some("a.txt".to_string()),
+                },
+                DiffFile { status: 'D', score: 0, path: "gone.txt".to_string(), orig_path: None },
+            ]
+        );
+        let mut opts = DiffOptions::new();
+        opts.name_status(true);
+        assert!(opts.parse_output("").unwrap().files.is_empty());
+        assert!(opts.parse_output("M\0").is_err());
+        assert!(opts.parse_output("R100\0old\0").is_err());
+        assert!(opts.parse_output("Rxx\0old\0new\0").is_err());
+        assert_eq!(
+            parse_name_status("MR\0b.txt\0RM\0c.txt\0").unwrap(),
+            vec![
+                DiffFile { status: 'M', score: 0, path: "b.txt".to_string(), orig_path: None },
+                DiffFile { status: 'R', score: 0, path: "c.txt".to_string(), orig_path: None },
+            ]
+        );
+        assert_eq!(DiffOptions::new().parse_output("patch").unwrap().patch, "patch");
+    }
+
+    #[test]
+    fn extra_args() {
+        let mut add = AddOptions::new();
+        add.pathspec("foo").extra_args(&["--renormalize"]);
+        assert_eq!(add.git_args(), vec!["add", "foo"]);
+        assert_eq!(add.extra(), ["--renormalize"]);
+        assert_eq!(add.args(), vec!["add", "foo", "--renormalize"]);
         assert_eq!(
-            AddOptions::new()
-                .all(false)
-                .chmod(false)
-                .pathspec("foobar")
-                .git_args(),
-            vec!["add", "--no-all", "--chmod=-x", "foobar"]
+            CommitOptions::new()
+                .message("tull")
+                .extra_args(&["--no-verify", "--signoff"])
+                .args(),
+            vec!["commit", "-q", "-m", "tull", "--no-verify", "--signoff"]
         );
+        // Extra arguments aren't taken as pathspecs
         assert_eq!(
-            AddOptions::new()
-                .all(true)
-                .chmod(true)
-                .pathspecs(&["foo", "bar"])
-                .git_args(),
-            vec!["add", "--all", "--chmod=+x", "foo", "bar"]
+            StatusOptions::new()
+                .pathspec("src")
+                .extra_args(&["--untracked-files=all"])
+                .args(),
+            vec![
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "--ignored",
+                "--untracked-files=all",
+                "--",
+                "src"
+            ]
         );
     }
 
     #[test]
     fn commit() {
         assert_eq!(CommitOptions::new().git_args(), vec!["commit", "-q"]);
+        assert_eq!(
+            CommitOptions::new_like_repository().git_args(),
+            vec!["commit", "-q", "--allow-empty"]
+        );
         assert_eq!(
             CommitOptions::new()
                 .message("tull")
@@ -802,6 +3893,250 @@ mod tests {
                 "bar.txt"
             ]
         );
+        assert_eq!(
+            CommitOptions::new()
+                .message("partial")
+                .only(true)
+                .files(vec!["foo.txt", "bar.txt"])
+                .git_args(),
+            vec!["commit", "-q", "-m", "partial", "--only", "foo.txt", "bar.txt"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .amend(true)
+                .no_edit(true)
+                .reset_author(true)
+                .git_args(),
+            vec!["commit", "-q", "--amend", "--no-edit", "--reset-author"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .message("")
+                .allow_empty_message(true)
+                .git_args(),
+            vec!["commit", "-q", "-m", "", "--allow-empty-message"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .reuse_message("HEAD")
+                .allow_empty_message(true)
+                .git_args(),
+            vec!["commit", "-q", "-C", "HEAD", "--allow-empty-message"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .amend(true)
+                .no_edit(true)
+                .file("foo.txt")
+                .git_args(),
+            vec!["commit", "-q", "--amend", "--no-edit", "foo.txt"]
+        );
+        assert_eq!(
+            CommitOptions::new().reuse_message("HEAD").git_args(),
+            vec!["commit", "-q", "-C", "HEAD"]
+        );
+        assert_eq!(
+            CommitOptions::new().reedit_message("HEAD~1").git_args(),
+            vec!["commit", "-q", "-c", "HEAD~1"]
+        );
+        assert_eq!(
+            CommitOptions::new().fixup("abc123").git_args(),
+            vec!["commit", "-q", "--fixup", "abc123"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .message("squashed")
+                .squash("abc123")
+                .git_args(),
+            vec!["commit", "-q", "-m", "squashed", "--squash", "abc123"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .message("# Heading")
+                .cleanup("verbatim")
+                .git_args(),
+            vec!["commit", "-q", "-m", "# Heading", "--cleanup=verbatim"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .message("many files")
+                .file("Makefile")
+                .pathspec_from_file("-")
+                .git_args(),
+            vec!["commit", "-q", "-m", "many files", "--pathspec-from-file", "-"]
+        );
+        assert_eq!(
+            CommitOptions::new()
+                .message("many files")
+                .pathspec_from_file("-")
+                .pathspec_file_nul(true)
+                .git_args(),
+            vec![
+                "commit",
+                "-q",
+                "-m",
+                "many files",
+                "--pathspec-from-file",
+                "-",
+                "--pathspec-file-nul"
+            ]
+        );
+    }
+
+    #[test]
+    fn log() {
+        assert_eq!(
+            LogOptions::new().git_args(),
+            vec!["log", "-z", "--no-decorate", "--format=medium"]
+        );
+        assert_eq!(
+            LogOptions::new()
+                .format(CommitFormat::Custom("%H %an".to_string()))
+                .max_count(5)
+                .revision("v1.0..HEAD")
+                .git_args(),
+            vec!["log", "-z", "--no-decorate", "--format=%H %an", "--max-count=5", "v1.0..HEAD"]
+        );
+        assert_eq!(
+            LogOptions::new().first_parent(true).revision("main").git_args(),
+            vec!["log", "-z", "--no-decorate", "--format=medium", "--first-parent", "main"]
+        );
+    }
+
+    #[test]
+    fn log_oneline() {
+        let out = "bebde4dfc9d06b00a6081db6aa71e74d21332305 Second commit\x000148ecaaf4de7cf72894420df84f5f4b808a88c0 First\0";
+        let commits = LogOptions::new()
+            .format(CommitFormat::OneLine)
+            .parse_output(out)
+            .unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].oid, "bebde4dfc9d06b00a6081db6aa71e74d21332305");
+        assert_eq!(commits[0].subject, "Second commit");
+        assert_eq!(commits[1].subject, "First");
+        assert!(commits[1].author.is_empty());
+    }
+
+    #[test]
+    fn log_medium_full() {
+        let out = "commit bebde4dfc9d06b00a6081db6aa71e74d21332305
+Author: MH Morgan <mh@morgan.no>
+Date:   Thu Oct 15 10:39:51 2020 +0200
+
+    Second commit
+
+    With a body
+\0commit 0148ecaaf4de7cf72894420df84f5f4b808a88c0
+Author: MH Morgan <mh@morgan.no>
+Date:   Thu Oct 15 10:34:42 2020 +0200
+
+    First
+";
+        let commits = LogOptions::new().parse_output(out).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(
+            commits[0],
+            Commit {
+                oid: "bebde4dfc9d06b00a6081db6aa71e74d21332305".to_string(),
+                author: "MH Morgan <mh@morgan.no>".to_string(),
+                date: "Thu Oct 15 10:39:51 2020 +0200".to_string(),
+                subject: "Second commit".to_string(),
+                text: out.split('\0').next().unwrap().trim_end().to_string(),
+                ..Default::default()
+            }
+        );
+
+        let out = "commit 0148ecaaf4de7cf72894420df84f5f4b808a88c0
+Author: MH Morgan <mh@morgan.no>
+Commit: Someone Else <someone@else.no>
+
+    First
+";
+        let commits = LogOptions::new().format(CommitFormat::Full).parse_output(out).unwrap();
+        assert_eq!(commits[0].committer, "Someone Else <someone@else.no>");
+        assert!(commits[0].date.is_empty());
+        assert!(LogOptions::new().parse_output("Author: nobody\n").is_err());
+    }
+
+    #[test]
+    fn log_detailed() {
+        let out = "\
+d6a4e0e4a2c4d1b4e8f4a3b2c1d0e9f8a7b6c5d4
+bebde4dfc9d06b00a6081db6aa71e74d21332305 0148ecaaf4de7cf72894420df84f5f4b808a88c0
+Magnus Hirth <magnus@web.com>
+Magnus Hirth <magnus@web.com>
+Sat Jun 13 12:00:00 2020 +0200
+Merge branch 'feature'
+Conflicts resolved by hand.
+
+Second paragraph.
+
+\x00bebde4dfc9d06b00a6081db6aa71e74d21332305
+0148ecaaf4de7cf72894420df84f5f4b808a88c0
+Magnus Hirth <magnus@web.com>
+Other Committer <other@web.com>
+Fri Jun 12 12:00:00 2020 +0200
+Second commit
+
+\x000148ecaaf4de7cf72894420df84f5f4b808a88c0
+
+Magnus Hirth <magnus@web.com>
+Magnus Hirth <magnus@web.com>
+Thu Jun 11 12:00:00 2020 +0200
+First
+\x00";
+        let mut opts = LogOptions::new();
+        opts.format(CommitFormat::Detailed);
+        let commits = opts.parse_output(out).unwrap();
+        assert_eq!(commits.len(), 3);
+
+        let merge = &commits[0];
+        assert_eq!(merge.oid, "d6a4e0e4a2c4d1b4e8f4a3b2c1d0e9f8a7b6c5d4");
+        assert_eq!(
+            merge.parents,
+            vec!["bebde4dfc9d06b00a6081db6aa71e74d21332305", "0148ecaaf4de7cf72894420df84f5f4b808a88c0"]
+        );
+        assert_eq!(merge.author, "Magnus Hirth <magnus@web.com>");
+        assert_eq!(merge.date, "Sat Jun 13 12:00:00 2020 +0200");
+        assert_eq!(merge.subject, "Merge branch 'feature'");
+        assert_eq!(merge.body, "Conflicts resolved by hand.\n\nSecond paragraph.");
+
+        assert_eq!(commits[1].parents, vec!["0148ecaaf4de7cf72894420df84f5f4b808a88c0"]);
+        assert_eq!(commits[1].committer, "Other Committer <other@web.com>");
+        assert_eq!(commits[1].body, "");
+        assert!(commits[2].parents.is_empty());
+        assert_eq!(commits[2].subject, "First");
+
+        assert!(opts.parse_output("d6a4e0e\nbebde4d\n").is_err());
+        assert_eq!(
+            opts.git_args(),
+            vec!["log", "-z", "--no-decorate", "--format=%H%n%P%n%an <%ae>%n%cn <%ce>%n%ad%n%s%n%b"]
+        );
+    }
+
+    #[test]
+    fn log_custom() {
+        let out = "bebde4d|Second commit\x000148eca|First\0";
+        let commits = LogOptions::new()
+            .format(CommitFormat::Custom("%h|%s".to_string()))
+            .parse_output(out)
+            .unwrap();
+        let texts: Vec<_> = commits.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["bebde4d|Second commit", "0148eca|First"]);
+        assert!(commits[0].oid.is_empty());
+    }
+
+    #[test]
+    fn maintenance() {
+        assert_eq!(MaintenanceOptions::run().git_args(), vec!["maintenance", "run"]);
+        assert_eq!(
+            MaintenanceOptions::run()
+                .auto(true)
+                .task("commit-graph")
+                .task("gc")
+                .git_args(),
+            vec!["maintenance", "run", "--auto", "--task=commit-graph", "--task=gc"]
+        );
     }
 
     #[test]
@@ -837,6 +4172,10 @@ mod tests {
                 .git_args(),
             vec!["pull", "-q", "--allow-unrelated", "origin", "master"]
         );
+        assert_eq!(
+            PullOptions::new().no_edit(true).depth(1).remote("origin").git_args(),
+            vec!["pull", "-q", "--no-edit", "--depth=1", "origin"]
+        );
     }
 
     #[test]
@@ -862,6 +4201,65 @@ mod tests {
                 "master"
             ]
         );
+        assert_eq!(
+            PushOptions::new()
+                .atomic(true)
+                .remote("origin")
+                .refspecs(["master", "v1.0"])
+                .git_args(),
+            vec!["push", "-q", "--atomic", "origin", "master", "v1.0"]
+        );
+        assert_eq!(
+            PushOptions::new().porcelain(true).remote("origin").git_args(),
+            vec!["push", "--porcelain", "origin"]
+        );
+
+        let out = "To github.com:MHmorgan/mhgit.git
+ \trefs/heads/master:refs/heads/master\t1a2b3c4..5d6e7f8
+*\trefs/heads/dev:refs/heads/dev\t[new branch]
+-\t:refs/heads/old\t[deleted]
+=\trefs/tags/v1.0:refs/tags/v1.0\t[up to date]
+!\trefs/heads/wip:refs/heads/wip\t[rejected] (non-fast-forward)
+Done
+";
+        let results = PushOptions::new().porcelain(true).parse_output(out).unwrap();
+        let flags: Vec<char> = results.iter().map(|r| r.flag).collect();
+        assert_eq!(flags, vec![' ', '*', '-', '=', '!']);
+        assert_eq!(
+            results[4],
+            PushResult {
+                flag: '!',
+                from: "refs/heads/wip".to_string(),
+                to: "refs/heads/wip".to_string(),
+                old_oid: String::new(),
+                new_oid: String::new(),
+                summary: "[rejected] (non-fast-forward)".to_string(),
+            }
+        );
+        assert_eq!(results[2].from, "");
+        assert!(results[4].is_rejected() && !results[0].is_rejected());
+        assert!(PushOptions::new().parse_output(out).unwrap().is_empty());
+        assert!(PushOptions::new().porcelain(true).parse_output("??\tfoo\tbar").is_err());
+    }
+
+    #[test]
+    fn reflog() {
+        assert_eq!(
+            ReflogOptions::expire(true, "30.days.ago").git_args(),
+            vec!["reflog", "expire", "--expire=30.days.ago", "--all"]
+        );
+        assert_eq!(
+            ReflogOptions::expire(false, "now").reference("refs/heads/old").git_args(),
+            vec!["reflog", "expire", "--expire=now", "refs/heads/old"]
+        );
+        assert_eq!(
+            ReflogOptions::delete("HEAD@{2}").git_args(),
+            vec!["reflog", "delete", "HEAD@{2}"]
+        );
+        assert_eq!(
+            ReflogOptions::delete("HEAD@{1}").reference("main@{3}").git_args(),
+            vec!["reflog", "delete", "HEAD@{1}", "main@{3}"]
+        );
     }
 
     #[test]
@@ -883,6 +4281,162 @@ mod tests {
                 "git://myrepo.com"
             ]
         );
+        let mut opts = RemoteOptions::add();
+        opts.mirror("fetch")
+            .name("backup")
+            .url("../backup.git")
+            .fetch_refspec("+refs/heads/*:refs/heads/*")
+            .fetch_refspec("+refs/tags/*:refs/tags/*");
+        assert_eq!(
+            opts.git_args(),
+            vec!["remote", "add", "--mirror=fetch", "backup", "../backup.git"]
+        );
+        assert_eq!(
+            opts.refspec_args(),
+            vec![
+                vec!["config", "--replace-all", "remote.backup.fetch", "+refs/heads/*:refs/heads/*"],
+                vec!["config", "--add", "remote.backup.fetch", "+refs/tags/*:refs/tags/*"],
+            ]
+        );
+        assert_eq!(
+            RemoteOptions::add().mirror("push").name("m").url("u").git_args(),
+            vec!["remote", "add", "--mirror=push", "m", "u"]
+        );
+        assert!(RemoteOptions::add().name("origin").refspec_args().is_empty());
+        assert_eq!(
+            RemoteOptions::prune().name("origin").git_args(),
+            vec!["remote", "prune", "origin"]
+        );
+        assert_eq!(
+            RemoteOptions::set_head().name("origin").git_args(),
+            vec!["remote", "set-head", "origin", "--auto"]
+        );
+        assert_eq!(
+            RemoteOptions::set_head().name("origin").branch("main").git_args(),
+            vec!["remote", "set-head", "origin", "main"]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(ResetOptions::hard().git_args(), vec!["reset", "-q", "--hard"]);
+        assert_eq!(
+            ResetOptions::soft().commit("HEAD~1").git_args(),
+            vec!["reset", "-q", "--soft", "HEAD~1"]
+        );
+        assert_eq!(
+            ResetOptions::mixed().commit("origin/master").git_args(),
+            vec!["reset", "-q", "--mixed", "origin/master"]
+        );
+    }
+
+    #[test]
+    fn rev_parse() {
+        assert_eq!(
+            RevParseOptions::new().revision("HEAD").revision("v1.0").git_args(),
+            vec!["rev-parse", "HEAD", "v1.0"]
+        );
+        assert_eq!(
+            RevParseOptions::new()
+                .verify(true)
+                .short(7)
+                .abbrev_ref(true)
+                .revisions(["HEAD"])
+                .git_args(),
+            vec!["rev-parse", "--verify", "--short=7", "--abbrev-ref", "HEAD"]
+        );
+        let out = "bebde4dfc9d06b00a6081db6aa71e74d21332305\n0148ecaaf4de7cf72894420df84f5f4b808a88c0\nbebde4dfc9d06b00a6081db6aa71e74d21332305\n";
+        assert_eq!(
+            RevParseOptions::new().revisions(["HEAD", "HEAD~1", "master"]).parse_output(out).unwrap(),
+            vec![
+                "bebde4dfc9d06b00a6081db6aa71e74d21332305",
+                "0148ecaaf4de7cf72894420df84f5f4b808a88c0",
+                "bebde4dfc9d06b00a6081db6aa71e74d21332305",
+            ]
+        );
+    }
+
+    #[test]
+    fn stash() {
+        assert_eq!(
+            StashOptions::push().message("wip").git_args(),
+            vec!["stash", "push", "-q", "-m", "wip"]
+        );
+        assert_eq!(StashOptions::pop().git_args(), vec!["stash", "pop", "-q"]);
+        assert_eq!(
+            StashOptions::apply().entry("stash@{1}").git_args(),
+            vec!["stash", "apply", "-q", "stash@{1}"]
+        );
+        assert_eq!(
+            StashOptions::pop().restore_index(true).entry("stash@{1}").git_args(),
+            vec!["stash", "pop", "-q", "--index", "stash@{1}"]
+        );
+        assert_eq!(
+            StashOptions::list().git_args(),
+            vec!["stash", "list", "--format=%gd%x00%gs"]
+        );
+
+        let out = "stash@{0}\0On master: Work in progress\nstash@{1}\0WIP on feature/foo: d43fb9b Add foo\n";
+        assert_eq!(
+            StashOptions::list().parse_output(out).unwrap(),
+            vec![
+                StashEntry {
+                    index: 0,
+                    name: "stash@{0}".to_string(),
+                    branch: "master".to_string(),
+                    message: "Work in progress".to_string(),
+                },
+                StashEntry {
+                    index: 1,
+                    name: "stash@{1}".to_string(),
+                    branch: "feature/foo".to_string(),
+                    message: "d43fb9b Add foo".to_string(),
+                },
+            ]
+        );
+        assert!(StashOptions::list().parse_output("stash@{x}\0On master: foo").is_err());
+        assert!(StashOptions::pop().parse_output("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            StatusOptions::new().git_args(),
+            vec!["status", "--porcelain=v2", "--branch", "--ignored"]
+        );
+        assert_eq!(
+            StatusOptions::new()
+                .pathspec("src")
+                .pathspecs(["foo", "bar"])
+                .git_args(),
+            vec![
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "--ignored",
+                "--",
+                "src",
+                "foo",
+                "bar"
+            ]
+        );
+    }
+
+    #[test]
+    fn submodule() {
+        assert_eq!(
+            SubmoduleOptions::update().git_args(),
+            vec!["submodule", "--quiet", "update", "--"]
+        );
+        assert_eq!(
+            SubmoduleOptions::update()
+                .init(true)
+                .recursive(true)
+                .jobs(4)
+                .path("libs/a")
+                .git_args(),
+            vec!["submodule", "--quiet", "update", "--init", "--recursive", "--jobs=4", "--", "libs/a"]
+        );
     }
 
     #[test]
@@ -895,9 +4449,138 @@ mod tests {
                 .git_args(),
             vec!["tag", "-m", "testen", "v1.0", "HEAD"]
         );
+        assert_eq!(
+            TagOptions::add().lightweight(true).tagname("v1.0").git_args(),
+            vec!["tag", "--no-sign", "v1.0"]
+        );
         assert_eq!(
             TagOptions::delete().tagname("v1.0").git_args(),
             vec!["tag", "-d", "v1.0"]
         );
     }
+
+    #[test]
+    fn tag_verify() {
+        assert_eq!(
+            TagVerifyOptions::new().tagname("v1.0").git_args(),
+            vec!["tag", "-v", "v1.0"]
+        );
+        assert_eq!(TagOptions::verify().tagname("v1.0").git_args(), vec!["tag", "-v", "v1.0"]);
+        assert_eq!(
+            TagVerifyOptions::new()
+                .tagname("v1.0")
+                .gpg_program("gpg2")
+                .args(),
+            vec!["-c", "gpg.program=gpg2", "tag", "-v", "v1.0"]
+        );
+    }
+
+    #[test]
+    fn tag_validate() {
+        assert!(TagOptions::add().msg("testen").tagname("v1.0").validate().is_ok());
+        assert!(TagOptions::add().lightweight(true).tagname("v1.0").validate().is_ok());
+        assert!(TagOptions::add().tagname("v1.0").extra_args(&["-F", "msg.txt"]).validate().is_ok());
+        assert!(TagOptions::delete().tagname("v1.0").validate().is_ok());
+
+        let err = TagOptions::add().tagname("v1.0").validate().unwrap_err();
+        assert!(err.to_string().contains("would open an editor"), "unexpected error: {}", err);
+        assert!(TagOptions::add()
+            .msg("testen")
+            .tagname("v1.0")
+            .extra_args(&["--edit"])
+            .validate()
+            .is_err());
+        assert!(TagOptions::add()
+            .lightweight(true)
+            .msg("testen")
+            .tagname("v1.0")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn tag_list() {
+        assert_eq!(TagListOptions::new().git_args(), vec!["tag", "--list"]);
+        assert_eq!(
+            TagListOptions::new()
+                .sort("-version:refname")
+                .merged("master")
+                .points_at("HEAD")
+                .pattern("v*")
+                .git_args(),
+            vec!["tag", "--list", "--sort=-version:refname", "--merged", "master", "--points-at", "HEAD", "v*"]
+        );
+        assert_eq!(
+            TagListOptions::new()
+                .sort("-version:refname")
+                .parse_output("v1.10.0\nv1.9.2\nv1.2.0\n")
+                .unwrap(),
+            vec!["v1.10.0", "v1.9.2", "v1.2.0"]
+        );
+        assert!(TagListOptions::new().parse_output("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn update_ref() {
+        assert_eq!(
+            UpdateRefOptions::create("refs/heads/foo", "1a2b3c").git_args(),
+            vec!["update-ref", "refs/heads/foo", "1a2b3c", ""]
+        );
+        assert_eq!(
+            UpdateRefOptions::delete("refs/heads/foo").git_args(),
+            vec!["update-ref", "-d", "refs/heads/foo"]
+        );
+        assert_eq!(
+            UpdateRefOptions::update("refs/heads/foo", "1a2b3c", "4d5e6f").git_args(),
+            vec!["update-ref", "refs/heads/foo", "1a2b3c", "4d5e6f"]
+        );
+    }
+
+    #[test]
+    fn worktree() {
+        assert_eq!(
+            WorktreeOptions::add("../hotfix").commit_ish("master").git_args(),
+            vec!["worktree", "add", "-q", "../hotfix", "master"]
+        );
+        assert_eq!(
+            WorktreeOptions::list().git_args(),
+            vec!["worktree", "list", "--porcelain"]
+        );
+
+        let out = "worktree /home/mh/mhgit
+HEAD 1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d
+branch refs/heads/master
+
+worktree /home/mh/hotfix
+HEAD 5d6e7f8a9b0c1d2e3f4a5d6e7f8a9b0c1d2e3f4a
+detached
+locked reason for locking
+prunable gitdir file points to non-existent location
+
+";
+        let worktrees = WorktreeOptions::list().parse_output(out).unwrap();
+        assert_eq!(
+            worktrees,
+            vec![
+                Worktree {
+                    path: PathBuf::from("/home/mh/mhgit"),
+                    head: "1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d".to_string(),
+                    branch: Some("master".to_string()),
+                    ..Default::default()
+                },
+                Worktree {
+                    path: PathBuf::from("/home/mh/hotfix"),
+                    head: "5d6e7f8a9b0c1d2e3f4a5d6e7f8a9b0c1d2e3f4a".to_string(),
+                    detached: true,
+                    locked: true,
+                    prunable: true,
+                    ..Default::default()
+                },
+            ]
+        );
+
+        let bare = WorktreeOptions::list().parse_output("worktree /srv/repo.git\nbare\n").unwrap();
+        assert!(bare[0].bare && bare[0].head.is_empty());
+        assert!(WorktreeOptions::list().parse_output("HEAD 1a2b3c\n").is_err());
+    }
 }
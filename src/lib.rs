@@ -37,30 +37,84 @@
 
 // Copyright 2020 Magnus Aa. Hirth. All rights reserved.
 
-#![allow(unused_imports, unused_variables, dead_code)]
+#![allow(unused_imports, unused_variables, dead_code, non_local_definitions)]
 
 #[macro_use]
 extern crate failure;
 
 use failure::{Fail, ResultExt};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, Stdio, Output};
+use std::sync::{mpsc, Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
 
+mod porcelain;
+mod rebase;
+mod remote;
+mod signature;
+mod state;
 mod status;
 pub mod commands;
 
-pub use status::Status;
+pub use porcelain::RefUpdate;
+pub use rebase::RebaseStatus;
+pub use remote::{PullBranch, PushRef, RemoteBranch, RemoteInfo};
+pub use signature::SignatureStatus;
+pub use state::{Operation, RepoState};
+pub use status::{Entry, PathDelta, Status, StatusDelta, StatusParseError};
 
 type Result<T> = std::result::Result<T, failure::Error>;
 
+/// Git subcommands which don't modify the repository. These don't take the
+/// lock of [locked](struct.Repository.html#method.locked) repositories, and
+/// are the only commands allowed in
+/// [read-only](struct.Repository.html#method.read_only) repositories.
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "blame",
+    "cat-file",
+    "check-attr",
+    "check-ignore",
+    "describe",
+    "diff",
+    "for-each-ref",
+    "log",
+    "ls-files",
+    "ls-tree",
+    "merge-base",
+    "rev-list",
+    "rev-parse",
+    "show",
+    "show-ref",
+    "status",
+    "verify-commit",
+];
+
+/// Git subcommands which only read the repository with some actions, given
+/// as the first argument after the subcommand. Like `READ_ONLY_COMMANDS`
+/// these don't take the lock and are allowed in read-only repositories.
+/// `git hash-object` is read-only without `-w`, and `git symbolic-ref` when
+/// only reading a ref.
+const READ_ONLY_ACTIONS: &[(&str, &[&str])] = &[
+    ("branch", &["--list", "-l", "--show-current"]),
+    ("config", &["--get", "--get-all", "--get-regexp", "--list", "-l"]),
+    ("remote", &["get-url", "show"]),
+    ("stash", &["list", "show"]),
+    ("tag", &["--list", "-l", "-v", "--verify"]),
+    ("worktree", &["list"]),
+];
+
 /// Git errors are returned when a git command fails.
 #[derive(Fail, Debug)]
 pub struct GitError {
     cmd: String,
     code: Option<i32>,
+    // Captured stdout, if any
+    stdout: String,
     #[cause] stderr: failure::Error,
 }
 
@@ -74,16 +128,193 @@ impl fmt::Display for GitError {
     }
 }
 
+impl GitError {
+    /// Exit code of the git command, if it wasn't stopped by a signal.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+/// Conflict errors are returned when a command fails because of merge
+/// conflicts, which must be resolved before continuing.
+#[derive(Fail, Debug)]
+#[fail(display = "{} resulted in conflicts", cmd)]
+pub struct ConflictError {
+    cmd: String,
+    #[cause] git: GitError,
+}
+
+/// Corrupt commit-graph errors are returned when `git commit-graph verify`
+/// finds the commit-graph file to be corrupt. Writing a new commit-graph
+/// replaces the corrupt file.
+#[derive(Fail, Debug)]
+#[fail(display = "{} found a corrupt commit-graph", cmd)]
+pub struct CorruptCommitGraphError {
+    cmd: String,
+    #[cause] git: GitError,
+}
+
+/// Dubious ownership errors are returned when git refuses to operate on a
+/// repository owned by another user, which is common in containers and CI.
+/// 
+/// Add the repository to the `safe.directory` git config, or use
+/// [`Repository::trust`] to allow it for commands run by this crate only.
+/// 
+/// [`Repository::trust`]: struct.Repository.html#method.trust
+#[derive(Fail, Debug)]
+#[fail(
+    display = "git refuses to use repository at {} owned by another user, add it to safe.directory or use Repository::trust",
+    path
+)]
+pub struct DubiousOwnershipError {
+    path: String,
+    #[cause] git: GitError,
+}
+
+impl DubiousOwnershipError {
+    /// Path of the untrusted repository, as reported by git.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+// Turn git errors caused by dubious repository ownership into dubious
+// ownership errors.
+fn dubious_ownership_error(e: failure::Error) -> failure::Error {
+    // fatal: detected dubious ownership in repository at '<path>'
+    let path = match e.downcast_ref::<GitError>() {
+        Some(err) => {
+            let stderr = err.stderr.to_string();
+            match stderr.split_once("detected dubious ownership in repository at '") {
+                Some((_, rest)) => rest.split('\'').next().unwrap_or_default().to_string(),
+                None => return e,
+            }
+        }
+        None => return e,
+    };
+    match e.downcast::<GitError>() {
+        Ok(git) => DubiousOwnershipError { path, git }.into(),
+        Err(e) => e,
+    }
+}
+
+// Turn git errors caused by merge conflicts into conflict errors.
+fn conflict_error(e: failure::Error) -> failure::Error {
+    let is_conflict = match e.downcast_ref::<GitError>() {
+        Some(err) => err.code == Some(1)
+            && (err.stdout.contains("CONFLICT") || err.stderr.to_string().contains("CONFLICT")),
+        None => false,
+    };
+    if !is_conflict {
+        return e;
+    }
+    match e.downcast::<GitError>() {
+        Ok(git) => ConflictError { cmd: git.cmd.clone(), git }.into(),
+        Err(e) => e,
+    }
+}
+
+// Turn git errors from verifying a commit-graph into corrupt commit-graph
+// errors, which git signals by exiting with 1.
+fn corrupt_commit_graph_error(e: failure::Error) -> failure::Error {
+    if !is_exit_one(&e) {
+        return e;
+    }
+    match e.downcast::<GitError>() {
+        Ok(git) => CorruptCommitGraphError { cmd: git.cmd.clone(), git }.into(),
+        Err(e) => e,
+    }
+}
+
+/// Git not found errors are returned when the git executable can't be found,
+/// usually because git isn't installed or isn't in `PATH`.
+#[derive(Fail, Debug)]
+#[fail(
+    display = "git executable {:?} not found, make sure git is installed and in PATH",
+    program
+)]
+pub struct GitNotFoundError {
+    program: PathBuf,
+}
+
+// Turn errors from starting git into git not found errors, if the executable
+// doesn't exist.
+fn exec_error(e: io::Error, program: &Path) -> failure::Error {
+    if e.kind() == io::ErrorKind::NotFound {
+        GitNotFoundError {
+            program: program.to_path_buf(),
+        }.into()
+    } else {
+        e.context("git execution failed").into()
+    }
+}
+
+/// Read-only errors are returned when a command which may modify the
+/// repository is run in a [read-only] repository.
+/// 
+/// [read-only]: struct.Repository.html#method.read_only
+#[derive(Fail, Debug)]
+#[fail(display = "{} is not allowed in a read-only repository", cmd)]
+pub struct ReadOnlyError {
+    cmd: String,
+}
+
+/// Output limit errors are returned when a git command produces more output
+/// than allowed by [`Repository::max_output_bytes`].
+/// 
+/// [`Repository::max_output_bytes`]: struct.Repository.html#method.max_output_bytes
+#[derive(Fail, Debug)]
+#[fail(display = "{} output exceeded limit of {} bytes", cmd, limit)]
+pub struct OutputLimitError {
+    cmd: String,
+    limit: usize,
+}
+
 /// GitOut indicates if git output should be piped or printed.
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum GitOut {
+    /// Print stdout and stderr to screen.
     Print,
+    /// Capture stdout, and stderr separately for errors.
+    #[default]
     Pipe,
+    /// Capture stdout and stderr interleaved, like `2>&1` in a shell. The
+    /// two are read separately, so output written at nearly the same time
+    /// may be captured out of order.
+    Combined,
+}
+
+/// MergeOutcome is the result of [`Repository::try_merge`].
+/// 
+/// [`Repository::try_merge`]: struct.Repository.html#method.try_merge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MergeOutcome {
+    /// The merge succeeded, or there was nothing to merge.
+    Merged,
+    /// The merge stopped at conflicts.
+    Conflicted,
+}
+
+/// Complete output of a git command, as returned by
+/// [`CommandOptions::run_full`].
+/// 
+/// [`CommandOptions::run_full`]: trait.CommandOptions.html#method.run_full
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommandOutput {
+    /// Captured stdout
+    pub stdout: String,
+
+    /// Captured stderr, like warnings and progress messages
+    pub stderr: String,
+
+    /// Exit code, or `None` if git was stopped by a signal
+    pub code: Option<i32>,
 }
 
-impl Default for GitOut {
-    fn default() -> Self {
-        GitOut::Pipe
+impl CommandOutput {
+    /// Returns true if git exited successfully.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
     }
 }
 
@@ -104,11 +335,61 @@ impl Default for GitOut {
 /// ```
 /// 
 /// [`at`]: struct.Repository.html#method.at
-#[derive(Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Repository {
     // Location of repository.
     location: Option<PathBuf>,
     stdout: GitOut,
+    // Max number of bytes of captured output.
+    max_output: Option<usize>,
+    // Don't pass -q to commands run by convenience methods.
+    verbose: bool,
+    // Serialize commands run in the repository.
+    locked: bool,
+    // Pass the location as safe.directory to every command.
+    trusted: bool,
+    // Refuse to run commands which may modify the repository.
+    read_only: bool,
+    // Git executable, if not git in PATH.
+    git: Option<PathBuf>,
+    // Let git open an editor, instead of setting GIT_EDITOR=true.
+    editor: bool,
+    // Pass the location with -C, instead of as working directory.
+    dash_c: bool,
+    // Run git with the user's locale, instead of setting LC_ALL=C.
+    user_locale: bool,
+}
+
+/// Builder for a [`Repository`] with several settings configured at once.
+/// 
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::RepositoryBuilder;
+/// use mhgit::GitOut::Print;
+/// let repo = RepositoryBuilder::new()
+///     .location("/home/mh/awesomeness")
+///     .gitout(Print)
+///     .quiet(false)
+///     .locked(true)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+/// 
+/// [`Repository`]: struct.Repository.html
+#[derive(Debug, Default, PartialEq, Eq, Hash)]
+pub struct RepositoryBuilder {
+    location: Option<PathBuf>,
+    stdout: GitOut,
+    max_output: Option<usize>,
+    verbose: bool,
+    locked: bool,
+    trusted: bool,
+    read_only: bool,
+    git: Option<PathBuf>,
+    editor: bool,
+    dash_c: bool,
+    user_locale: bool,
 }
 
 /// Trait implemented by all command option struct ([`CommitOptions`], [`PushOptions`], etc.)
@@ -123,6 +404,56 @@ pub trait CommandOptions {
     /// The vector contains at least one element, which is the name of the subcommand.
     fn git_args(&self) -> Vec<&str>;
 
+    /// Return extra raw arguments passed to git after the [`git_args`].
+    /// 
+    /// This is an escape hatch for options without a builder method. By
+    /// default there are none. Implementors keeping extra arguments, like the
+    /// `extra_args` builders of the [`commands`] types, override this method
+    /// to expose them:
+    /// 
+    /// ```rust,ignore
+    /// fn extra(&self) -> &[String] {
+    ///     &self.extra_args
+    /// }
+    /// ```
+    /// 
+    /// [`git_args`]: #tymethod.git_args
+    /// [`commands`]: commands/index.html
+    fn extra(&self) -> &[String] {
+        &[]
+    }
+
+    /// Return config overrides for this invocation, as `<name>=<value>`.
+    /// 
+    /// These are passed with `-c` before the subcommand, and don't change any
+    /// config files. By default there are none.
+    fn config(&self) -> &[String] {
+        &[]
+    }
+
+    /// Return all arguments passed to git: the [`config`] overrides, followed
+    /// by the [`git_args`] and the [`extra`] arguments.
+    /// 
+    /// If the git arguments contain a `--` separator the extra arguments are
+    /// inserted before it, so they are never taken as paths.
+    /// 
+    /// [`config`]: #method.config
+    /// [`git_args`]: #tymethod.git_args
+    /// [`extra`]: #method.extra
+    fn args(&self) -> Vec<&str> {
+        let mut args = self.git_args();
+        let extra = self.extra().iter().map(|a| a.as_str());
+        match args.iter().position(|a| *a == "--") {
+            Some(i) => {
+                args.splice(i..i, extra);
+            }
+            None => args.extend(extra),
+        }
+        let config = self.config().iter().flat_map(|c| vec!["-c", c.as_str()]);
+        args.splice(0..0, config);
+        args
+    }
+
     /// Parse the captured stdout into an appropriate rust type.
     fn parse_output(&self, out: &str) -> Result<Self::Output>;
 
@@ -144,10 +475,64 @@ pub trait CommandOptions {
     /// ```
     /// 
     fn run(&self, repo: &Repository) -> Result<Self::Output> {
-        let args = self.git_args();
+        let args = self.args();
         let out = repo.run(args)?;
         self.parse_output(&out)
     }
+
+    /// Run the command in the given git repository, returning the captured
+    /// stdout without parsing it.
+    /// 
+    /// Useful for output dropped by the [`parse_output`] of the command.
+    /// Commands run by a custom [`run`] may not produce the same output.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::{Repository, CommandOptions};
+    /// use mhgit::commands::AddOptions;
+    /// let repo = Repository::new();
+    /// let would_add = AddOptions::new()
+    ///                           .all(true)
+    ///                           .extra_args(&["--dry-run"])
+    ///                           .run_raw(&repo)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`parse_output`]: #tymethod.parse_output
+    /// [`run`]: #method.run
+    fn run_raw(&self, repo: &Repository) -> Result<String> {
+        repo.run(self.args())
+    }
+
+    /// Run the command in the given git repository, returning the captured
+    /// stdout and stderr together with the exit code.
+    /// 
+    /// Unlike [`run`], git exiting with an error isn't an error, so warnings
+    /// printed by successful commands can be inspected, as well as failures.
+    /// Both stdout and stderr are always captured, whatever the
+    /// [`Repository::gitout`] setting, and the output isn't limited by
+    /// [`Repository::max_output_bytes`].
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::{Repository, CommandOptions};
+    /// use mhgit::commands::PullOptions;
+    /// let repo = Repository::new();
+    /// let out = PullOptions::new().run_full(&repo)?;
+    /// for warning in out.stderr.lines().filter(|l| l.starts_with("warning:")) {
+    ///     println!("{}", warning);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`run`]: #method.run
+    /// [`Repository::gitout`]: struct.Repository.html#method.gitout
+    /// [`Repository::max_output_bytes`]: struct.Repository.html#method.max_output_bytes
+    fn run_full(&self, repo: &Repository) -> Result<CommandOutput> {
+        repo.run_full(self.args())
+    }
 }
 
 impl Repository {
@@ -197,188 +582,1789 @@ impl Repository {
         git_dir.exists() && git_dir.is_dir()
     }
 
-    /// Configure if the output of git commands run in this repo should be
-    /// piped or printed to screen. 
+    /// Return true if the repository location is inside a work tree.
     /// 
-    /// Piping is default. 
+    /// Returns an error if the location isn't in a git repository at all.
+    pub fn is_inside_work_tree(&self) -> Result<bool> {
+        let args = vec!["rev-parse", "--is-inside-work-tree"];
+        self.run_bool(args)
+    }
+
+    /// Return true if the repository is bare.
+    /// 
+    /// Returns an error if the location isn't in a git repository at all.
+    pub fn is_bare(&self) -> Result<bool> {
+        let args = vec!["rev-parse", "--is-bare-repository"];
+        self.run_bool(args)
+    }
+
+    /// Return true if `name` is a configured git alias.
+    pub fn is_alias(&self, name: &str) -> Result<bool> {
+        Ok(self.alias_target(name)?.is_some())
+    }
+
+    /// Return the command an alias expands to, or `None` if `name` isn't
+    /// a configured alias.
     /// 
     /// ```rust,no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// use mhgit::Repository;
-    /// use mhgit::GitOut::Print;
-    /// Repository::new()
-    ///     .gitout(Print)
-    ///     .status()?;
+    /// if let Some(target) = Repository::new().alias_target("st")? {
+    ///     println!("st is an alias for {}", target);
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn gitout(&mut self, val: GitOut) -> &mut Repository {
-        self.stdout = val;
-        self
+    pub fn alias_target(&self, name: &str) -> Result<Option<String>> {
+        let key = format!("alias.{}", name);
+        let args = vec!["config", "--get", &key];
+        match self.run(args) {
+            Ok(out) => Ok(Some(out.trim_end().to_string())),
+            // git config exits with 1 if the key isn't set
+            Err(e) if is_exit_one(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
     }
 
-    /// Run `git add` in the repository.
-    /// 
-    /// The command is called with the --all option. To call `git add` with
-    /// different options use [`AddOptions`].
+    /// Return true if HEAD is at `rev` and there are no staged or unstaged
+    /// changes to tracked files. Untracked files are ignored.
     /// 
-    /// [`AddOptions`]: commands/struct.AddOptions.html
-    pub fn add(&mut self) -> Result<&mut Self> {
-        let args = vec!["add", "--all"];
-        self.run(args)?;
-        Ok(self)
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if !Repository::at("/srv/app")?.is_at("v1.2.0")? {
+    ///     println!("deployment doesn't match v1.2.0");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_at(&self, rev: &str) -> Result<bool> {
+        let commit = format!("{}^{{commit}}", rev);
+        let head = self.run(vec!["rev-parse", "--verify", "HEAD"])?;
+        let target = self.run(vec!["rev-parse", "--verify", &commit])?;
+        if head != target {
+            return Ok(false);
+        }
+        Ok(self.run_check(vec!["diff", "--quiet"])?
+            && self.run_check(vec!["diff", "--cached", "--quiet"])?)
     }
 
-    /// Run `git commit` in the repository, with the given commit message.
+    /// Return true if `path` is ignored by `.gitignore` or other exclude
+    /// files, as checked by `git check-ignore`.
     /// 
-    /// The command is called with --allow-empty, avoiding errors if no changes
-    /// were added since last commit. To call `git commit` with different
-    /// options use [`CommitOptions`].
+    /// The path doesn't need to exist. Tracked files are never ignored.
     /// 
-    /// [`CommitOptions`]: commands/struct.CommitOptions.html
-    pub fn commit(&mut self, msg: &str) -> Result<&mut Self> {
-        let args = vec!["commit", "-m", msg, "-q", "--allow-empty"];
-        self.run(args)?;
-        Ok(self)
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if !Repository::new().is_ignored("target/debug")? {
+    ///     println!("add target/ to .gitignore");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_ignored(&self, path: &str) -> Result<bool> {
+        self.run_check(vec!["check-ignore", "-q", "--", path])
     }
 
-    /// Run `git fetch` in the repository.
+    /// Return true if a merge is in progress, meaning `MERGE_HEAD` exists in
+    /// the [`git_dir`].
     /// 
-    /// The command is called with --all
-    pub fn fetch(&mut self) -> Result<&mut Self> {
-        let args = vec!["fetch", "--all", "-q"];
-        self.run(args)?;
-        Ok(self)
-    }
-
-    /// Run `git init`, initializing the repository.
-    pub fn init(&mut self) -> Result<&mut Self> {
-        // Create the directory if it doesn't already exist
-        if let Some(loc) = &self.location {
-            if !loc.exists() {
-                fs::create_dir_all(loc)?;
-            }
-        }
-        let args = vec!["init", "-q"];
-        self.run(args)?;
-        Ok(self)
+    /// [`git_dir`]: #method.git_dir
+    pub fn is_merging(&self) -> Result<bool> {
+        Ok(state::is_merging(&self.git_dir()?))
     }
 
-    /// Run `git notes add`, adding a note to HEAD.
+    /// Return true if a rebase is in progress. See [`rebase_status`] for the
+    /// details of the rebase.
     /// 
-    /// To call `git notes` with different optinos use [`NotesOptions`].
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let repo = Repository::new();
+    /// if repo.is_merging()? || repo.is_rebasing()? || repo.is_cherry_picking()? {
+    ///     println!("finish the current operation first");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     /// 
-    /// [`NotesOptions`]: commands/struct.NotesOptions.html
-    pub fn notes(&mut self, msg: &str) -> Result<&mut Self> {
-        let args = vec!["notes", "add", "-m", msg];
-        self.run(args)?;
-        Ok(self)
+    /// [`rebase_status`]: #method.rebase_status
+    pub fn is_rebasing(&self) -> Result<bool> {
+        Ok(state::is_rebasing(&self.git_dir()?))
     }
 
-    /// Run `git pull` without specifying remote or refs.
+    /// Return true if a cherry-pick is in progress, meaning
+    /// `CHERRY_PICK_HEAD` exists in the [`git_dir`].
     /// 
-    /// To call `git pull` with different options use [`PullOptions`].
-    /// 
-    /// [`PullOptions`]: commands/struct.PullOptions.html
-    pub fn pull(&mut self) -> Result<&mut Self> {
-        let args = vec!["pull", "-q"];
-        self.run(args)?;
-        Ok(self)
+    /// [`git_dir`]: #method.git_dir
+    pub fn is_cherry_picking(&self) -> Result<bool> {
+        Ok(state::is_cherry_picking(&self.git_dir()?))
     }
 
-    /// Run `git push` without specifying remote or refs.
+    /// Configure if the output of git commands run in this repo should be
+    /// piped or printed to screen. 
     /// 
-    /// To call `git push` with different options use [`PushOptions`].
+    /// Piping is default. 
     /// 
-    /// [`PushOptions`]: commands/struct.PushOptions.html
-    pub fn push(&mut self) -> Result<&mut Self> {
-        let args = vec!["push", "-q"];
-        self.run(args)?;
-        Ok(self)
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// use mhgit::GitOut::Print;
+    /// Repository::new()
+    ///     .gitout(Print)
+    ///     .status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn gitout(&mut self, val: GitOut) -> &mut Repository {
+        self.stdout = val;
+        self
     }
 
-    /// Run `git remote add` in the repository.
+    /// Configure if the convenience methods ([`commit`], [`pull`], [`push`],
+    /// etc.) should run git with the `-q` option.
     /// 
-    /// This adds a single remote to the repository. To call `git remote`
-    /// with different options use [`RemoteOptions`].
+    /// Quiet is default. Turning it off is mostly useful in combination with
+    /// [`GitOut::Print`], to see the normal progress output of git.
     /// 
-    /// [`RemoteOptions`]: commands/struct.RemoteOptions.html
-    pub fn remote(&mut self, name: &str, url: &str) -> Result<&mut Self> {
-        let args = vec!["remote", "add", name, url];
-        self.run(args)?;
-        Ok(self)
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// use mhgit::GitOut::Print;
+    /// Repository::new()
+    ///     .gitout(Print)
+    ///     .quiet(false)
+    ///     .pull()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`commit`]: #method.commit
+    /// [`pull`]: #method.pull
+    /// [`push`]: #method.push
+    /// [`GitOut::Print`]: enum.GitOut.html#variant.Print
+    pub fn quiet(&mut self, val: bool) -> &mut Repository {
+        self.verbose = !val;
+        self
     }
 
-    /// Run `git status` parsing the status into idiomatic Rust type.
+    /// Configure if git commands run in this repo should be serialized.
     /// 
-    /// The status information is returned in a [`Status`].
+    /// Git commands modifying the repository will fail if another git
+    /// process holds the index lock. When locked, commands run through any
+    /// `Repository` at the same location wait for each other instead.
+    /// Read-only commands, like `status`, `log` and `rev-parse`, don't take
+    /// the lock.
     /// 
-    /// [`Status`]: struct.Status.html
-    pub fn status(&self) -> Result<Status> {
-        let args = vec!["status", "--porcelain=v2", "--branch", "--ignored"];
-        let out = self.run(args)?;
-        Status::try_from(out.as_str())
-    }
-
-    /// Run `git stash` in the repository.
+    /// This only serializes commands run by this process.
     /// 
-    /// The command is run without ony options.
-    pub fn stash(&mut self) -> Result<&mut Self> {
-        let args = vec!["stash", "-q"];
-        self.run(args)?;
-        Ok(self)
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::{CommandOptions, Repository};
+    /// use mhgit::commands::AddOptions;
+    /// use std::thread;
+    /// let mut repo = Repository::new();
+    /// repo.locked(true);
+    /// let repo = &repo;
+    /// thread::scope(|s| {
+    ///     s.spawn(|| AddOptions::new().pathspec("foo.txt").run(repo));
+    ///     s.spawn(|| AddOptions::new().pathspec("bar.txt").run(repo));
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn locked(&mut self, val: bool) -> &mut Repository {
+        self.locked = val;
+        self
     }
 
-    /// Run `git tag`, creating a new tag object.
+    /// Trust the repository, even if it's owned by another user.
     /// 
-    /// To call `git tag` with different options use [`TagOptions`].
+    /// Git refuses to operate on repositories owned by other users, unless
+    /// they're listed in the `safe.directory` config, and fails with a
+    /// [`DubiousOwnershipError`]. Trusting the repository passes
+    /// `-c safe.directory=<location>` to every command, without changing any
+    /// config files. The location must be the top-level of the work tree.
     /// 
-    /// [`TagOptions`]: commands/struct.TagOptions.html
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let status = Repository::at("/builds/project")?
+    ///     .trust()
+    ///     .status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`DubiousOwnershipError`]: struct.DubiousOwnershipError.html
+    pub fn trust(&mut self) -> &mut Repository {
+        self.trusted = true;
+        self
+    }
+
+    /// Limit the number of bytes of output captured from git commands run in
+    /// this repo.
+    /// 
+    /// If a command produces more output the command is killed and an
+    /// [`OutputLimitError`] is returned. This only applies when output is
+    /// piped.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .max_output_bytes(1 << 20)
+    ///     .status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`OutputLimitError`]: struct.OutputLimitError.html
+    pub fn max_output_bytes(&mut self, limit: usize) -> &mut Repository {
+        self.max_output = Some(limit);
+        self
+    }
+
+    /// Set the git executable used to run commands. By default `git` is
+    /// looked up in `PATH`.
+    /// 
+    /// If the executable doesn't exist commands fail with a
+    /// [`GitNotFoundError`].
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .git_binary("/opt/git/bin/git")
+    ///     .status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`GitNotFoundError`]: struct.GitNotFoundError.html
+    pub fn git_binary<P: AsRef<Path>>(&mut self, path: P) -> &mut Repository {
+        self.git = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Configure if git may open an editor, like for a commit without a
+    /// message.
+    /// 
+    /// By default `GIT_EDITOR` and `GIT_SEQUENCE_EDITOR` are set to `true`
+    /// for all commands, so git never waits for an editor. The editor exits
+    /// immediately, and git continues with the default message, or fails if
+    /// a message is required. Allow the editor for interactive use, together
+    /// with [`GitOut::Print`].
+    /// 
+    /// [`GitOut::Print`]: enum.GitOut.html#variant.Print
+    pub fn editor(&mut self, val: bool) -> &mut Repository {
+        self.editor = val;
+        self
+    }
+
+    /// Configure if git should be run with `-C <location>`, rather than with
+    /// the location as working directory.
+    /// 
+    /// Either way git runs in the repository, but with `-C` the working
+    /// directory is inherited from this process. This matters for commands
+    /// reading the working directory, like hooks and aliases run by git, and
+    /// relative paths passed as `--git-dir` or `--work-tree`. Pathspecs are
+    /// relative to the location in both cases.
+    pub fn dash_c(&mut self, val: bool) -> &mut Repository {
+        self.dash_c = val;
+        self
+    }
+
+    /// Configure if git should run with the user's locale.
+    /// 
+    /// By default `LC_ALL` and `LANG` are set to `C` for all commands, so
+    /// git output is in English whatever the user's language. Errors are
+    /// classified by the messages git prints, which only works in English.
+    /// This also affects the messages seen with [`GitOut::Print`], so use
+    /// the user's locale for interactive use.
+    /// 
+    /// [`GitOut::Print`]: enum.GitOut.html#variant.Print
+    pub fn user_locale(&mut self, val: bool) -> &mut Repository {
+        self.user_locale = val;
+        self
+    }
+
+    /// Configure if the repository is read-only.
+    /// 
+    /// A read-only repository refuses to run commands which may modify it,
+    /// returning a [`ReadOnlyError`] without running git. Only commands
+    /// known to never modify the repository are allowed, like `status`,
+    /// `log`, `diff`, `show` and `rev-parse`, and listing actions like
+    /// `branch --list`, `config --get`, `stash list` and `remote get-url`.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let mut repo = Repository::new();
+    /// repo.read_only(true);
+    /// assert!(repo.status().is_ok());
+    /// assert!(repo.commit("Not allowed").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`ReadOnlyError`]: struct.ReadOnlyError.html
+    pub fn read_only(&mut self, val: bool) -> &mut Repository {
+        self.read_only = val;
+        self
+    }
+
+    /// Run `git add` in the repository.
+    /// 
+    /// The command is called with the --all option. To call `git add` with
+    /// different options use [`AddOptions`].
+    /// 
+    /// [`AddOptions`]: commands/struct.AddOptions.html
+    pub fn add(&mut self) -> Result<&mut Self> {
+        let args = vec!["add", "--all"];
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Return the blob oid the file at `path` in the work tree hashes to,
+    /// using `git hash-object`. Nothing is written to the object database.
+    /// 
+    /// Comparing it with the oid in the index or a commit tells if the file
+    /// content differs.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let repo = Repository::new();
+    /// let oid = repo.blob_oid("Cargo.toml")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn blob_oid(&self, path: &str) -> Result<String> {
+        let out = self.run(vec!["hash-object", "--", path])?;
+        Ok(out.trim_end().to_string())
+    }
+
+    /// Return true if `commit` is reachable from `branch`, which means the
+    /// branch contains the commit.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if Repository::new().branch_contains("production", "v1.2")? {
+    ///     println!("v1.2 is deployed");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn branch_contains(&self, branch: &str, commit: &str) -> Result<bool> {
+        let args = vec!["merge-base", "--is-ancestor", commit, branch];
+        self.run_check(args)
+    }
+
+    /// Return the names of all local branches containing `commit`.
+    /// 
+    /// To list branches with other filters use [`BranchOptions`].
+    /// 
+    /// [`BranchOptions`]: commands/struct.BranchOptions.html
+    pub fn branches_containing(&self, commit: &str) -> Result<Vec<String>> {
+        commands::BranchOptions::list().contains(commit).run(self)
+    }
+
+    /// Return the paths changed between the revisions `from` and `to`, as
+    /// listed by `git diff --name-only from..to`.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for path in Repository::new().changed_files_between("v1.0.0", "v1.1.0")? {
+    ///     println!("changed: {}", path);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn changed_files_between(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let range = format!("{}..{}", from, to);
+        let out = self.run(vec!["diff", "--name-only", "-z", &range, "--"])?;
+        Ok(out.split_terminator('\0').map(String::from).collect())
+    }
+
+    /// Return the git attributes `attrs` of `path`, as pairs of attribute
+    /// name and value, as given by `git check-attr`.
+    /// 
+    /// The value is `set`, `unset`, `unspecified` or the value assigned in
+    /// `.gitattributes`. Use [`check_attr_all`] to get all attributes set
+    /// for the path.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for (attr, val) in Repository::new().check_attr(&["filter", "export-ignore"], "logo.png")? {
+    ///     println!("{}: {}", attr, val);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`check_attr_all`]: #method.check_attr_all
+    pub fn check_attr(&self, attrs: &[&str], path: &str) -> Result<Vec<(String, String)>> {
+        let mut args = vec!["check-attr"];
+        args.extend(attrs);
+        args.push("--");
+        args.push(path);
+        parse_check_attr(&self.run(args)?)
+    }
+
+    /// Return all git attributes set for `path`, as given by
+    /// `git check-attr --all`. Unspecified attributes aren't included.
+    /// 
+    /// See [`check_attr`] for checking specific attributes.
+    /// 
+    /// [`check_attr`]: #method.check_attr
+    pub fn check_attr_all(&self, path: &str) -> Result<Vec<(String, String)>> {
+        parse_check_attr(&self.run(vec!["check-attr", "--all", "--", path])?)
+    }
+
+    /// Run `git checkout <target>`, switching to a branch or detaching HEAD
+    /// at a commit.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .checkout("develop")?
+    ///     .pull()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkout(&mut self, target: &str) -> Result<&mut Self> {
+        let mut args = self.quiet_args(vec!["checkout"]);
+        args.push(target);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git checkout --detach <rev>`, detaching HEAD at the commit `rev`,
+    /// even if `rev` is a branch.
+    /// 
+    /// The revision is checked with [`commit_exists`] first, failing with a
+    /// clear error if it doesn't name a commit.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new().checkout_detached("3f2c1b0")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`commit_exists`]: #method.commit_exists
+    pub fn checkout_detached(&mut self, rev: &str) -> Result<&mut Self> {
+        if !self.commit_exists(rev)? {
+            bail!("can't detach HEAD at {}, no such commit", rev);
+        }
+        let mut args = self.quiet_args(vec!["checkout", "--detach"]);
+        args.push(rev);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git checkout <rev> -- <paths>`, restoring the files at `paths`
+    /// in the index and work tree to their content in `rev`. HEAD is not
+    /// changed.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new().checkout_paths("HEAD~1", &["Cargo.toml", "src"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkout_paths(&mut self, rev: &str, paths: &[&str]) -> Result<&mut Self> {
+        let mut args = self.quiet_args(vec!["checkout"]);
+        args.push(rev);
+        args.push("--");
+        args.extend_from_slice(paths);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git commit` in the repository, with the given commit message.
+    /// 
+    /// The command is called with --allow-empty, avoiding errors if no changes
+    /// were added since last commit. To call `git commit` with different
+    /// options use [`CommitOptions`], which doesn't allow empty commits unless
+    /// created with [`new_like_repository`].
+    /// 
+    /// [`CommitOptions`]: commands/struct.CommitOptions.html
+    /// [`new_like_repository`]: commands/struct.CommitOptions.html#method.new_like_repository
+    pub fn commit(&mut self, msg: &str) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["commit", "-m", msg, "--allow-empty"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run [`add`] and [`commit`], committing all changes in the work tree,
+    /// including new and deleted files. Like [`commit`], the commit is made
+    /// even if there are no changes.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .commit_all("Update generated files")?
+    ///     .push()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`add`]: #method.add
+    /// [`commit`]: #method.commit
+    pub fn commit_all(&mut self, msg: &str) -> Result<&mut Self> {
+        self.add()?.commit(msg)
+    }
+
+    /// Return true if `rev` names a commit. Trees, blobs and unknown
+    /// revisions give false.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if !Repository::new().commit_exists("v1.2")? {
+    ///     println!("v1.2 is not a commit");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn commit_exists(&self, rev: &str) -> Result<bool> {
+        let commit = format!("{}^{{commit}}", rev);
+        self.run_check(vec!["rev-parse", "-q", "--verify", &commit])
+    }
+
+    /// Return the files changed by the commit `rev`, as pairs of status
+    /// letter and path.
+    /// 
+    /// The status letters are those of `git show --name-status`, like `A`
+    /// added, `M` modified, `D` deleted and `R` renamed. Renamed and copied
+    /// files are reported with their new path.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for (status, path) in Repository::new().commit_files("HEAD")? {
+    ///     println!("{} {}", status, path);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn commit_files(&self, rev: &str) -> Result<Vec<(char, String)>> {
+        let out = self.run(vec!["show", "--name-status", "-z", "--format=", rev])?;
+        let files = commands::parse_name_status(&out)?;
+        Ok(files.into_iter().map(|f| (f.status, f.path)).collect())
+    }
+
+    /// Return all effective config entries as key-value pairs, as listed by
+    /// `git config --list`.
+    /// 
+    /// Keys with several values are listed once per value, in config order.
+    /// Values may contain newlines. A key without a value, like a boolean
+    /// `[section] key`, has an empty value.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for (key, val) in Repository::new().config_list()? {
+    ///     println!("{} = {}", key, val);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn config_list(&self) -> Result<Vec<(String, String)>> {
+        let out = self.run(vec!["config", "--list", "-z"])?;
+        Ok(parse_config_list(&out))
+    }
+
+    /// Return the paths with unresolved merge conflicts, as listed by
+    /// `git diff --name-only --diff-filter=U`.
+    /// 
+    /// This is quicker than reading the [`unmerged`] entries of the full
+    /// status, when only the paths are needed.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for path in Repository::new().conflicted_files()? {
+    ///     println!("both modified: {}", path);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`unmerged`]: struct.Status.html#structfield.unmerged
+    pub fn conflicted_files(&self) -> Result<Vec<String>> {
+        let out = self.run(vec!["diff", "--name-only", "--diff-filter=U", "-z"])?;
+        Ok(out.split_terminator('\0').map(String::from).collect())
+    }
+
+    /// Run `git checkout -b <name> [<start_point>]`, creating a new branch
+    /// and switching to it. Without a start point the branch is created at
+    /// HEAD.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .create_branch("hotfix", Some("v1.2.0"))?
+    ///     .commit_all("Fix crash on startup")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_branch(&mut self, name: &str, start_point: Option<&str>) -> Result<&mut Self> {
+        let mut args = self.quiet_args(vec!["checkout", "-b", name]);
+        if let Some(start) = start_point {
+            args.push(start);
+        }
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Delete all local branches merged into `into`, returning the names of
+    /// the deleted branches.
+    /// 
+    /// The current branch, `into` itself and the `protected` branches are
+    /// never deleted. Branches are deleted with `git branch -d`, so only
+    /// fully merged branches can be deleted.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let deleted = Repository::new()
+    ///     .delete_merged_branches("master", &["develop", "release"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn delete_merged_branches(&mut self, into: &str, protected: &[&str]) -> Result<Vec<String>> {
+        // Current branch, if not detached
+        let current = match self.run(vec!["symbolic-ref", "--short", "-q", "HEAD"]) {
+            Ok(out) => Some(out.trim().to_string()),
+            Err(e) if is_exit_one(&e) => None,
+            Err(e) => return Err(e),
+        };
+
+        let merged = commands::BranchOptions::list().merged(into).run(self)?;
+        let mut deleted = Vec::new();
+        for branch in merged {
+            if branch == into
+                || Some(&branch) == current.as_ref()
+                || protected.contains(&branch.as_str())
+            {
+                continue;
+            }
+            self.run(vec!["branch", "-d", &branch])?;
+            deleted.push(branch);
+        }
+        Ok(deleted)
+    }
+
+    /// Run `git fetch` in the repository.
+    /// 
+    /// The command is called with --all
+    pub fn fetch(&mut self) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["fetch", "--all"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git fetch <remote> <refspec>`, fetching a single ref.
+    /// 
+    /// Use a refspec like `main:refs/remotes/origin/main` to store the
+    /// fetched ref locally, otherwise it's only available as `FETCH_HEAD`.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new().fetch_refspec("origin", "main:refs/remotes/origin/main")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fetch_refspec(&mut self, remote: &str, refspec: &str) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["fetch", remote, refspec]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Return the number of lines in a file at the given revision. If `rev`
+    /// is `None` the file is read from the work tree, whether it's tracked,
+    /// untracked or ignored.
+    /// 
+    /// A last line without a trailing newline is counted.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let repo = Repository::new();
+    /// let before = repo.file_line_count("src/lib.rs", Some("HEAD"))?;
+    /// let after = repo.file_line_count("src/lib.rs", None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn file_line_count(&self, path: &str, rev: Option<&str>) -> Result<usize> {
+        let data = match rev {
+            Some(rev) => self.show_file(rev, path)?,
+            None => {
+                let full_path = match &self.location {
+                    Some(loc) => loc.join(path),
+                    None => PathBuf::from(path),
+                };
+                fs::read(&full_path).with_context(|_| format!("failed to read {}", path))?
+            }
+        };
+        let mut count = data.iter().filter(|b| **b == b'\n').count();
+        if !data.is_empty() && !data.ends_with(b"\n") {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Return the absolute path of the git directory, as reported by
+    /// `git rev-parse --git-dir`.
+    /// 
+    /// This is usually `.git` in the work tree, but linked worktrees and
+    /// submodules have their git directory elsewhere.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let hooks = Repository::new().git_dir()?.join("hooks");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn git_dir(&self) -> Result<PathBuf> {
+        let out = self.run(vec!["rev-parse", "--git-dir"])?;
+        let dir = PathBuf::from(out.trim_end_matches('\n'));
+        if dir.is_absolute() {
+            return Ok(dir);
+        }
+        let base = match &self.location {
+            Some(loc) => loc.clone(),
+            None => std::env::current_dir().context("failed to get current dir")?,
+        };
+        Ok(base.join(dir))
+    }
+
+    /// Run `git init`, initializing the repository.
+    pub fn init(&mut self) -> Result<&mut Self> {
+        // Create the directory if it doesn't already exist
+        if let Some(loc) = &self.location {
+            if !loc.exists() {
+                fs::create_dir_all(loc)?;
+            }
+        }
+        let args = self.quiet_args(vec!["init"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Return the most recent tag reachable from HEAD, as found by
+    /// `git describe --tags --abbrev=0`, or `None` if there is no such tag.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// match Repository::new().latest_tag()? {
+    ///     Some(tag) => println!("changes since {}", tag),
+    ///     None => println!("nothing released yet"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn latest_tag(&self) -> Result<Option<String>> {
+        match self.run(vec!["describe", "--tags", "--abbrev=0"]) {
+            Ok(out) => Ok(Some(out.trim_end().to_string())),
+            Err(e) => {
+                // No tags at all, or none reachable from HEAD
+                let no_tags = e.downcast_ref::<GitError>().is_some_and(|err| {
+                    let stderr = err.stderr.to_string();
+                    stderr.contains("No names found") || stderr.contains("No tags can describe")
+                });
+                if no_tags {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Return the patterns tracked by Git LFS, like `git lfs track` lists
+    /// them. These are the patterns with `filter=lfs` in the top-level
+    /// `.gitattributes` of the work tree.
+    /// 
+    /// The Git LFS binary isn't needed. Attributes set in other
+    /// `.gitattributes` files aren't included, use [`check_attr`] to check
+    /// the `filter` attribute of a single path.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// for pattern in Repository::new().lfs_tracked()? {
+    ///     println!("{} (Git LFS)", pattern);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`check_attr`]: #method.check_attr
+    pub fn lfs_tracked(&self) -> Result<Vec<String>> {
+        let top = self.run(vec!["rev-parse", "--show-toplevel"])?;
+        let path = Path::new(top.trim_end()).join(".gitattributes");
+        match fs::read_to_string(&path) {
+            Ok(txt) => Ok(parse_lfs_patterns(&txt)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.context(format!("failed to read {}", path.display())).into()),
+        }
+    }
+
+    /// Run `git merge --abort`, aborting a merge which stopped at conflicts,
+    /// like after a failed [`pull`] or [`try_merge`]. The work tree and index
+    /// are restored to their state before the merge.
+    /// 
+    /// An error is returned without running git if no merge is in progress.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let mut repo = Repository::new();
+    /// if repo.pull().is_err() && repo.is_merging()? {
+    ///     repo.merge_abort()?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`pull`]: #method.pull
+    /// [`try_merge`]: #method.try_merge
+    pub fn merge_abort(&mut self) -> Result<&mut Self> {
+        if !self.is_merging()? {
+            bail!("no merge in progress to abort");
+        }
+        self.run(vec!["merge", "--abort"])?;
+        Ok(self)
+    }
+
+    /// Run `git notes add`, adding a note to HEAD.
+    /// 
+    /// To call `git notes` with different optinos use [`NotesOptions`].
+    /// 
+    /// [`NotesOptions`]: commands/struct.NotesOptions.html
+    pub fn notes(&mut self, msg: &str) -> Result<&mut Self> {
+        let args = vec!["notes", "add", "-m", msg];
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Create and switch to a new orphan branch, without any history.
+    /// 
+    /// If `clear` is true the index and work tree is emptied of tracked
+    /// files, using `git switch --orphan`. Otherwise they are kept, using
+    /// `git checkout --orphan`, and the first commit contains the current
+    /// files. Untracked files are always kept. `git switch` can't keep the
+    /// files, which is why the two modes use different commands.
+    /// 
+    /// Note that `clear = true` needs git 2.23 or later.
+    /// 
+    /// The branch doesn't exist before the first commit is made on it.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .orphan_branch("gh-pages", true)?
+    ///     .commit("Initial gh-pages commit")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn orphan_branch(&mut self, name: &str, clear: bool) -> Result<&mut Self> {
+        let mut args = self.quiet_args(vec![if clear { "switch" } else { "checkout" }]);
+        args.push("--orphan");
+        args.push(name);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git pull` without specifying remote or refs.
+    /// 
+    /// Merges use the default merge message (`--no-edit`). To call
+    /// `git pull` with different options use [`PullOptions`].
+    /// 
+    /// [`PullOptions`]: commands/struct.PullOptions.html
+    pub fn pull(&mut self) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["pull", "--no-edit"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git push` without specifying remote or refs.
+    /// 
+    /// To call `git push` with different options use [`PushOptions`].
+    /// 
+    /// [`PushOptions`]: commands/struct.PushOptions.html
+    pub fn push(&mut self) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["push"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Return the state of the rebase in progress, or `None` if not rebasing.
+    /// 
+    /// The state is read from the git directory, rather than from git
+    /// output.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if let Some(rebase) = Repository::new().rebase_status()? {
+    ///     println!("step {} of {}", rebase.step, rebase.total);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebase_status(&self) -> Result<Option<RebaseStatus>> {
+        RebaseStatus::read(&self.git_dir()?)
+    }
+
+    /// Run `git remote add` in the repository.
+    /// 
+    /// This adds a single remote to the repository. To call `git remote`
+    /// with different options use [`RemoteOptions`].
+    /// 
+    /// [`RemoteOptions`]: commands/struct.RemoteOptions.html
+    pub fn remote(&mut self, name: &str, url: &str) -> Result<&mut Self> {
+        let args = vec!["remote", "add", name, url];
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Return the default branch of a remote, which is the branch its HEAD
+    /// points to.
+    /// 
+    /// The local `refs/remotes/<remote>/HEAD` is used if set, otherwise the
+    /// remote is queried with `git remote show`.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let branch = Repository::new().remote_default_branch("origin")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remote_default_branch(&self, remote: &str) -> Result<String> {
+        let head = format!("refs/remotes/{}/HEAD", remote);
+        let prefix = format!("refs/remotes/{}/", remote);
+        if let Ok(out) = self.run(vec!["symbolic-ref", "-q", &head]) {
+            if let Some(branch) = out.trim_end().strip_prefix(&prefix) {
+                return Ok(branch.to_string());
+            }
+        }
+
+        // Symbolic ref isn't set, ask the remote
+        self.remote_info(remote)?
+            .head_branch
+            .ok_or_else(|| format_err!("no default branch found for remote {}", remote))
+    }
+
+    /// Run `git remote show`, returning information about the remote
+    /// and how local branches track it.
+    /// 
+    /// The information is returned in a [`RemoteInfo`].
+    /// 
+    /// [`RemoteInfo`]: struct.RemoteInfo.html
+    pub fn remote_info(&self, remote: &str) -> Result<RemoteInfo> {
+        let args = vec!["remote", "show", remote];
+        let out = self.run(args)?;
+        RemoteInfo::try_from(out.as_str())
+    }
+
+    /// Return the URL of a remote, as given by `git remote get-url`. With
+    /// `for_push` the push URL is returned, which is the fetch URL unless
+    /// `remote.<name>.pushurl` is set.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let repo = Repository::new();
+    /// println!("fetching from {}", repo.remote_url("origin", false)?);
+    /// println!("pushing to {}", repo.remote_url("origin", true)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remote_url(&self, name: &str, for_push: bool) -> Result<String> {
+        let mut args = vec!["remote", "get-url"];
+        if for_push {
+            args.push("--push");
+        }
+        args.push(name);
+        Ok(self.run(args)?.trim_end().to_string())
+    }
+
+    /// Run `git reset --hard <rev>`, resetting HEAD, the index and the work
+    /// tree to `rev`.
+    /// 
+    /// Uncommitted changes to tracked files are lost. To call `git reset`
+    /// with different options use [`ResetOptions`].
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// Repository::new()
+    ///     .fetch()?
+    ///     .reset_hard("origin/master")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`ResetOptions`]: commands/struct.ResetOptions.html
+    pub fn reset_hard(&mut self, rev: &str) -> Result<&mut Self> {
+        commands::ResetOptions::hard().commit(rev).run(self)?;
+        Ok(self)
+    }
+
+    /// Run `git reset --mixed <rev>`, resetting HEAD and the index to `rev`.
+    /// The work tree is left unchanged.
+    /// 
+    /// To call `git reset` with different options use [`ResetOptions`].
+    /// 
+    /// [`ResetOptions`]: commands/struct.ResetOptions.html
+    pub fn reset_mixed(&mut self, rev: &str) -> Result<&mut Self> {
+        commands::ResetOptions::mixed().commit(rev).run(self)?;
+        Ok(self)
+    }
+
+    /// Run `git reset --soft <rev>`, resetting HEAD to `rev`. The index and
+    /// work tree are left unchanged.
+    /// 
+    /// To call `git reset` with different options use [`ResetOptions`].
+    /// 
+    /// [`ResetOptions`]: commands/struct.ResetOptions.html
+    pub fn reset_soft(&mut self, rev: &str) -> Result<&mut Self> {
+        commands::ResetOptions::soft().commit(rev).run(self)?;
+        Ok(self)
+    }
+
+    /// Return a summary of the repository state: the current branch, how far
+    /// it's ahead and behind its upstream, if the work tree is dirty and any
+    /// operation in progress.
+    /// 
+    /// This runs `git status` and reads the [`git_dir`] once, rather than
+    /// calling [`status`], [`is_merging`], [`is_rebasing`] and
+    /// [`is_cherry_picking`] separately.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let state = Repository::new().state()?;
+    /// if let Some(op) = state.operation {
+    ///     println!("{:?} in progress", op);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`git_dir`]: #method.git_dir
+    /// [`status`]: #method.status
+    /// [`is_merging`]: #method.is_merging
+    /// [`is_rebasing`]: #method.is_rebasing
+    /// [`is_cherry_picking`]: #method.is_cherry_picking
+    pub fn state(&self) -> Result<RepoState> {
+        let status = self.status()?;
+        let operation = Operation::read(&self.git_dir()?);
+        Ok(RepoState::new(&status, operation))
+    }
+
+    /// Run `git status` parsing the status into idiomatic Rust type.
+    /// 
+    /// The status information is returned in a [`Status`]. To limit the
+    /// status to some paths use [`StatusOptions`].
+    /// 
+    /// [`Status`]: struct.Status.html
+    /// [`StatusOptions`]: commands/struct.StatusOptions.html
+    pub fn status(&self) -> Result<Status> {
+        commands::StatusOptions::new().run(self)
+    }
+
+    /// Return the content of a file at the given revision, by running
+    /// `git show <rev>:<path>`.
+    /// 
+    /// The content is returned as raw bytes, since the file isn't necessarily
+    /// valid utf-8.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let old_readme = Repository::new().show_file("HEAD~1", "README.md")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn show_file(&self, rev: &str, path: &str) -> Result<Vec<u8>> {
+        let object = format!("{}:{}", rev, path);
+        let args = vec!["show", &object];
+        self.run_bytes(args).map_err(|e| {
+            e.context(format!("failed to show {} at revision {}", path, rev))
+             .into()
+        })
+    }
+
+    /// Run `git stash` in the repository.
+    /// 
+    /// The command is run without ony options.
+    pub fn stash(&mut self) -> Result<&mut Self> {
+        let args = self.quiet_args(vec!["stash"]);
+        self.run(args)?;
+        Ok(self)
+    }
+
+    /// Run `git stash list`, returning all stash entries, newest first.
+    /// 
+    /// To call `git stash` with different options use [`StashOptions`].
+    /// 
+    /// [`StashOptions`]: commands/struct.StashOptions.html
+    pub fn stash_list(&self) -> Result<Vec<commands::StashEntry>> {
+        commands::StashOptions::list().run(self)
+    }
+
+    /// Run `git stash pop`, applying and removing the latest stash entry.
+    /// 
+    /// If applying the stash results in conflicts a [`ConflictError`] is
+    /// returned, and the stash entry is kept.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::{ConflictError, Repository};
+    /// let mut repo = Repository::new();
+    /// if let Err(e) = repo.stash_pop() {
+    ///     if e.downcast_ref::<ConflictError>().is_some() {
+    ///         println!("resolve the conflicts, then drop the stash");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`ConflictError`]: struct.ConflictError.html
+    pub fn stash_pop(&mut self) -> Result<&mut Self> {
+        commands::StashOptions::pop().run(self)?;
+        Ok(self)
+    }
+
+    /// Run `git stash pop --index`, like [`stash_pop`], but changes which
+    /// were staged when stashed are staged again.
+    /// 
+    /// [`stash_pop`]: #method.stash_pop
+    pub fn stash_pop_keep_index(&mut self) -> Result<&mut Self> {
+        commands::StashOptions::pop().restore_index(true).run(self)?;
+        Ok(self)
+    }
+
+    /// Run `git tag --no-sign`, creating a new lightweight tag.
+    /// 
+    /// To call `git tag` with different options use [`TagOptions`].
+    /// 
+    /// [`TagOptions`]: commands/struct.TagOptions.html
     pub fn tag(&mut self, tagname: &str) -> Result<&mut Self> {
-        let args = vec!["tag", tagname];
-        self.run(args)?;
+        commands::TagOptions::add()
+            .lightweight(true)
+            .tagname(tagname)
+            .run(self)?;
         Ok(self)
     }
 
-    fn run(&self, args: Vec<&str>) -> Result<String> {
-        // Setup command
-        let mut cmd = Command::new("git");
+    /// Return true if the tag `name` exists.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let mut repo = Repository::new();
+    /// if !repo.tag_exists("v1.2.0")? {
+    ///     repo.tag("v1.2.0")?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tag_exists(&self, name: &str) -> Result<bool> {
+        let tag = format!("refs/tags/{}", name);
+        self.run_check(vec!["rev-parse", "-q", "--verify", &tag])
+    }
+
+    /// Run `git merge --no-edit` merging `rev` into the current branch.
+    /// 
+    /// If the merge stops at conflicts [`MergeOutcome::Conflicted`] is
+    /// returned. With `abort_on_conflict` the merge is then aborted with
+    /// `git merge --abort`, restoring the work tree and index, and otherwise
+    /// the conflicts are left to be resolved. Other failures are returned as
+    /// errors.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::{MergeOutcome, Repository};
+    /// let mut repo = Repository::new();
+    /// if repo.try_merge("origin/master", true)? == MergeOutcome::Conflicted {
+    ///     println!("origin/master must be merged manually");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`MergeOutcome::Conflicted`]: enum.MergeOutcome.html#variant.Conflicted
+    pub fn try_merge(&mut self, rev: &str, abort_on_conflict: bool) -> Result<MergeOutcome> {
+        let args = self.quiet_args(vec!["merge", "--no-edit", rev]);
+        match self.run(args).map_err(conflict_error) {
+            Ok(_) => Ok(MergeOutcome::Merged),
+            Err(e) if e.downcast_ref::<ConflictError>().is_some() => {
+                if abort_on_conflict {
+                    self.merge_abort()
+                        .map_err(|e| e.context(format!("failed to abort merge of {}", rev)))?;
+                }
+                Ok(MergeOutcome::Conflicted)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Verify the GPG signature of the commit `rev` with
+    /// `git verify-commit --raw`.
+    /// 
+    /// An unsigned commit or bad signature is not an error, but gives a
+    /// status which isn't valid. An error is returned if `rev` isn't a
+    /// commit.
+    /// 
+    /// The status is read from stderr, which doesn't count towards
+    /// [`max_output_bytes`]. The limit on stdout is checked after git exits.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// if !Repository::new().verify_commit("origin/master")?.valid {
+    ///     println!("origin/master is not signed");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`max_output_bytes`]: #method.max_output_bytes
+    pub fn verify_commit(&self, rev: &str) -> Result<SignatureStatus> {
+        // Git exits with 1 for missing commits too
+        if !self.commit_exists(rev)? {
+            bail!("can't verify {}, no such commit", rev);
+        }
+        // The status lines are printed to stderr, which is only kept by
+        // run_full
+        let out = self.run_full(vec!["verify-commit", "--raw", rev])?;
+        if let Some(limit) = self.max_output.filter(|l| out.stdout.len() > *l) {
+            return Err(OutputLimitError {
+                cmd: String::from("git verify-commit"),
+                limit,
+            }.into());
+        }
+        match out.code {
+            Some(0) => Ok(SignatureStatus::from_raw(&out.stderr, true)),
+            Some(1) => Ok(SignatureStatus::from_raw(&out.stderr, false)),
+            code => Err(dubious_ownership_error(GitError {
+                cmd: String::from("git verify-commit"),
+                code,
+                stdout: out.stdout,
+                stderr: format_err!("{}", out.stderr),
+            }.into())),
+        }
+    }
+
+    /// Run `git worktree add`, creating a worktree at `path` with `branch`
+    /// checked out. A relative path is relative to the repository location.
+    /// 
+    /// Returns a repository at the new worktree, with the same settings as
+    /// this repository. To call `git worktree` with different options use
+    /// [`WorktreeOptions`].
+    /// 
+    /// ```rust,no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use mhgit::Repository;
+    /// let mut repo = Repository::new();
+    /// let status = repo.worktree_add("../hotfix", "master")?.status()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// 
+    /// [`WorktreeOptions`]: commands/struct.WorktreeOptions.html
+    pub fn worktree_add(&mut self, path: &str, branch: &str) -> Result<Repository> {
+        commands::WorktreeOptions::add(path).commit_ish(branch).run(self)?;
+        let path = match &self.location {
+            Some(loc) => loc.join(path),
+            None => PathBuf::from(path),
+        };
+        Ok(Repository {
+            location: Repository::at(path)?.location,
+            ..self.clone()
+        })
+    }
+
+    /// Run `git worktree list`, returning all worktrees of the repository.
+    /// The main worktree is first.
+    /// 
+    /// To call `git worktree` with different options use [`WorktreeOptions`].
+    /// 
+    /// [`WorktreeOptions`]: commands/struct.WorktreeOptions.html
+    pub fn worktree_list(&self) -> Result<Vec<commands::Worktree>> {
+        commands::WorktreeOptions::list().run(self)
+    }
+
+    // Setup the git command to run with the given arguments.
+    fn command(&self, args: &[&str]) -> Result<Command> {
+        let mut cmd = Command::new(self.git_program());
         cmd.stdin(Stdio::inherit());
         if matches!(self.stdout, GitOut::Print) {
             cmd.stdout(Stdio::inherit())
                .stderr(Stdio::inherit());
         }
         if let Some(path) = &self.location {
-            (&mut cmd).current_dir(path);
+            if self.dash_c {
+                cmd.arg("-C").arg(path);
+            } else {
+                cmd.current_dir(path);
+            }
         }
-        (&mut cmd).args(&args);
+        if !self.editor {
+            cmd.env("GIT_EDITOR", "true")
+               .env("GIT_SEQUENCE_EDITOR", "true");
+        }
+        if !self.user_locale {
+            cmd.env("LC_ALL", "C")
+               .env("LANG", "C");
+        }
+        if self.trusted {
+            let path = match &self.location {
+                Some(path) => path.clone(),
+                None => std::env::current_dir().context("failed to get current dir")?,
+            };
+            cmd.arg("-c").arg(format!("safe.directory={}", path.display()));
+        }
+        cmd.args(args);
+        Ok(cmd)
+    }
 
-        if matches!(self.stdout, GitOut::Print) {
+    // Return the git executable to run.
+    fn git_program(&self) -> &Path {
+        self.git.as_deref().unwrap_or_else(|| Path::new("git"))
+    }
+
+    // Append -q to the arguments, unless quiet is turned off.
+    fn quiet_args<'a>(&self, mut args: Vec<&'a str>) -> Vec<&'a str> {
+        if !self.verbose {
+            args.push("-q");
+        }
+        args
+    }
+
+    fn run(&self, args: Vec<&str>) -> Result<String> {
+        let out = self.run_bytes(args)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    // Run git returning true if it succeeds, and false if it exits with 1.
+    fn run_check(&self, args: Vec<&str>) -> Result<bool> {
+        match self.run(args) {
+            Ok(_) => Ok(true),
+            Err(e) if is_exit_one(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Run git parsing a true/false output.
+    fn run_bool(&self, args: Vec<&str>) -> Result<bool> {
+        let out = self.run(args)?;
+        match out.trim() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => bail!("unexpected git output {:?}, expected true or false", other),
+        }
+    }
+
+    // Run git capturing stdout and stderr, whatever the exit status.
+    fn run_full(&self, args: Vec<&str>) -> Result<CommandOutput> {
+        let lock = self.command_lock(&args)?;
+        let _guard = lock.as_ref().map(|l| l.lock().unwrap_or_else(PoisonError::into_inner));
+
+        let mut cmd = self.command(&args)?;
+        cmd.stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+        let out = cmd.output().map_err(|e| exec_error(e, self.git_program()))?;
+        Ok(CommandOutput {
+            stdout: String::from_utf8(out.stdout)?,
+            stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+            code: out.status.code(),
+        })
+    }
+
+    // Check that the command may be run, returning the repository lock to
+    // hold while running modifying commands.
+    fn command_lock(&self, args: &[&str]) -> Result<Option<Arc<Mutex<()>>>> {
+        let read_only = is_read_only(args);
+        if self.read_only && !read_only {
+            return Err(ReadOnlyError {
+                cmd: format!("git {}", subcommand(args)),
+            }.into());
+        }
+        if self.locked && !read_only {
+            let loc = self.location.clone().unwrap_or_else(|| PathBuf::from("."));
+            Ok(Some(repository_lock(loc)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Run git returning the raw captured stdout, which may not be valid utf-8.
+    fn run_bytes(&self, args: Vec<&str>) -> Result<Vec<u8>> {
+        let subcmd = subcommand(&args);
+        // Hold the repository lock while running modifying commands
+        let lock = self.command_lock(&args)?;
+        let _guard = lock.as_ref().map(|l| l.lock().unwrap_or_else(PoisonError::into_inner));
+
+        let program = self.git_program();
+        let mut cmd = self.command(&args)?;
+        let res = if matches!(self.stdout, GitOut::Print) {
             // Run with inherited stdin/out
-            let status = cmd.status().context("git execution failed")?;
+            let status = cmd.status().map_err(|e| exec_error(e, program))?;
             if status.success() {
-                return Ok(String::new())
+                Ok(Vec::new())
             } else {
                 Err(GitError {
-                    cmd: format!("git {}", args[0]),
+                    cmd: format!("git {}", subcmd),
                     code: status.code(),
+                    stdout: String::new(),
                     stderr: format_err!("check stderr output"),
                 }.into())
             }
+        } else if matches!(self.stdout, GitOut::Combined) {
+            // Run with stdout and stderr piped together
+            self.run_combined(cmd, &args)
+        } else if let Some(limit) = self.max_output {
+            // Run with piped stdin/out, and limited output
+            self.run_limited(cmd, &args, limit)
         } else {
             // Run with piped stdin/out
-            let out = cmd.output().context("git execution failed")?;
+            let out = cmd.output().map_err(|e| exec_error(e, program))?;
             if out.status.success() {
-                Ok(String::from_utf8(out.stdout)?)
+                Ok(out.stdout)
             } else {
                 Err(GitError {
-                    cmd: format!("git {}", args[0]),
+                    cmd: format!("git {}", subcmd),
                     code: out.status.code(),
+                    stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
                     stderr: format_err!("{}", std::str::from_utf8(&out.stderr)?),
                 }.into())
             }
+        };
+        res.map_err(dubious_ownership_error)
+    }
+
+    fn run_combined(&self, mut cmd: Command, args: &[&str]) -> Result<Vec<u8>> {
+        cmd.stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+        let mut child = cmd.spawn().map_err(|e| exec_error(e, Path::new(cmd.get_program())))?;
+
+        // Collect chunks from both pipes in the order they are read
+        let (tx, rx) = mpsc::channel();
+        forward_chunks(child.stdout.take().expect("stdout is piped"), tx.clone());
+        forward_chunks(child.stderr.take().expect("stderr is piped"), tx);
+
+        let mut out = Vec::new();
+        for chunk in rx {
+            out.extend_from_slice(&chunk);
+            if let Some(limit) = self.max_output.filter(|l| out.len() > *l) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(OutputLimitError {
+                    cmd: format!("git {}", subcommand(args)),
+                    limit,
+                }.into());
+            }
+        }
+
+        let status = child.wait().context("git execution failed")?;
+        if status.success() {
+            Ok(out)
+        } else {
+            Err(GitError {
+                cmd: format!("git {}", subcommand(args)),
+                code: status.code(),
+                stdout: String::new(),
+                stderr: format_err!("{}", String::from_utf8_lossy(&out)),
+            }.into())
+        }
+    }
+
+    fn run_limited(&self, mut cmd: Command, args: &[&str], limit: usize) -> Result<Vec<u8>> {
+        cmd.stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+        let mut child = cmd.spawn().map_err(|e| exec_error(e, Path::new(cmd.get_program())))?;
+
+        // Drain stderr in the background, so git doesn't block on a full pipe
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr = thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf);
+            buf
+        });
+
+        // Read one byte past the limit to detect overflow
+        let mut stdout = Vec::new();
+        child.stdout.take()
+             .expect("stdout is piped")
+             .take(limit as u64 + 1)
+             .read_to_end(&mut stdout)?;
+        if stdout.len() > limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(OutputLimitError {
+                cmd: format!("git {}", subcommand(args)),
+                limit,
+            }.into());
+        }
+
+        let status = child.wait().context("git execution failed")?;
+        let stderr = stderr.join().unwrap_or_default();
+        if status.success() {
+            Ok(stdout)
+        } else {
+            Err(GitError {
+                cmd: format!("git {}", subcommand(args)),
+                code: status.code(),
+                stdout: String::from_utf8_lossy(&stdout).into_owned(),
+                stderr: format_err!("{}", std::str::from_utf8(&stderr)?),
+            }.into())
+        }
+    }
+}
+
+impl RepositoryBuilder {
+    /// Create a new repository builder, with default settings.
+    pub fn new() -> RepositoryBuilder {
+        RepositoryBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Set location of the repository. The current directory is default.
+    pub fn location<P: AsRef<Path>>(&mut self, path: P) -> &mut RepositoryBuilder {
+        self.location = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// See [`Repository::gitout`](struct.Repository.html#method.gitout).
+    pub fn gitout(&mut self, val: GitOut) -> &mut RepositoryBuilder {
+        self.stdout = val;
+        self
+    }
+
+    /// See [`Repository::quiet`](struct.Repository.html#method.quiet).
+    pub fn quiet(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.verbose = !val;
+        self
+    }
+
+    /// See [`Repository::max_output_bytes`](struct.Repository.html#method.max_output_bytes).
+    pub fn max_output_bytes(&mut self, limit: usize) -> &mut RepositoryBuilder {
+        self.max_output = Some(limit);
+        self
+    }
+
+    /// See [`Repository::locked`](struct.Repository.html#method.locked).
+    pub fn locked(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.locked = val;
+        self
+    }
+
+    /// See [`Repository::trust`](struct.Repository.html#method.trust).
+    pub fn trust(&mut self) -> &mut RepositoryBuilder {
+        self.trusted = true;
+        self
+    }
+
+    /// See [`Repository::git_binary`](struct.Repository.html#method.git_binary).
+    pub fn git_binary<P: AsRef<Path>>(&mut self, path: P) -> &mut RepositoryBuilder {
+        self.git = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// See [`Repository::editor`](struct.Repository.html#method.editor).
+    pub fn editor(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.editor = val;
+        self
+    }
+
+    /// See [`Repository::dash_c`](struct.Repository.html#method.dash_c).
+    pub fn dash_c(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.dash_c = val;
+        self
+    }
+
+    /// See [`Repository::user_locale`](struct.Repository.html#method.user_locale).
+    pub fn user_locale(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.user_locale = val;
+        self
+    }
+
+    /// See [`Repository::read_only`](struct.Repository.html#method.read_only).
+    pub fn read_only(&mut self, val: bool) -> &mut RepositoryBuilder {
+        self.read_only = val;
+        self
+    }
+
+    /// Build the repository.
+    /// 
+    /// Fails if the location can't be canonicalized, like [`Repository::at`].
+    /// 
+    /// [`Repository::at`]: struct.Repository.html#method.at
+    pub fn build(&self) -> Result<Repository> {
+        let mut repo = match &self.location {
+            Some(path) => Repository::at(path)?,
+            None => Repository::new(),
+        };
+        repo.gitout(self.stdout)
+            .quiet(!self.verbose)
+            .locked(self.locked)
+            .editor(self.editor)
+            .dash_c(self.dash_c)
+            .user_locale(self.user_locale)
+            .read_only(self.read_only);
+        if let Some(limit) = self.max_output {
+            repo.max_output_bytes(limit);
+        }
+        if self.trusted {
+            repo.trust();
+        }
+        if let Some(git) = &self.git {
+            repo.git_binary(git);
+        }
+        Ok(repo)
+    }
+}
+
+// Return the subcommand of git arguments, skipping any leading -c overrides.
+fn subcommand<'a>(args: &[&'a str]) -> &'a str {
+    split_subcommand(args).0
+}
+
+// Split the git arguments into the subcommand and the arguments after it.
+fn split_subcommand<'a, 'b>(args: &'b [&'a str]) -> (&'a str, &'b [&'a str]) {
+    let mut i = 0;
+    while i + 1 < args.len() && args[i] == "-c" {
+        i += 2;
+    }
+    match args.get(i) {
+        Some(subcmd) => (subcmd, &args[i + 1..]),
+        None => ("", &[]),
+    }
+}
+
+// Return true if the git arguments run a command which doesn't modify the
+// repository. See READ_ONLY_COMMANDS and READ_ONLY_ACTIONS.
+fn is_read_only(args: &[&str]) -> bool {
+    let (subcmd, rest) = split_subcommand(args);
+    if READ_ONLY_COMMANDS.contains(&subcmd) {
+        return true;
+    }
+    if subcmd == "hash-object" {
+        return !rest.iter().take_while(|a| **a != "--").any(|a| *a == "-w");
+    }
+    if subcmd == "symbolic-ref" {
+        // A second ref or -d writes
+        let (opts, refs): (Vec<&str>, Vec<&str>) = rest.iter().partition(|a| a.starts_with('-'));
+        return refs.len() == 1 && opts.iter().all(|o| ["-q", "--quiet", "--short"].contains(o));
+    }
+    match (READ_ONLY_ACTIONS.iter().find(|(cmd, _)| *cmd == subcmd), rest.first()) {
+        (Some((_, actions)), Some(action)) => actions.contains(action),
+        _ => false,
+    }
+}
+
+// Parse the output of git check-attr, with lines of <path>: <attr>: <value>,
+// returning the attributes and values. Paths may contain ": ", but attributes
+// and values don't.
+fn parse_check_attr(out: &str) -> Result<Vec<(String, String)>> {
+    let mut attrs = Vec::new();
+    for line in out.lines().filter(|l| !l.is_empty()) {
+        let mut fields = line.rsplitn(3, ": ");
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(val), Some(attr), Some(_path)) => attrs.push((attr.to_string(), val.to_string())),
+            _ => bail!("bad check-attr format: {}", line),
+        }
+    }
+    Ok(attrs)
+}
+
+// Parse a .gitattributes file, returning the patterns with filter=lfs.
+fn parse_lfs_patterns(txt: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    for line in txt.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("[attr]") {
+            continue;
+        }
+        // Quoted patterns may contain spaces
+        let (pattern, attrs) = match line.strip_prefix('"').and_then(|l| l.split_once('"')) {
+            Some((pattern, attrs)) => (pattern, attrs),
+            None => line.split_once(char::is_whitespace).unwrap_or((line, "")),
+        };
+        if attrs.split_whitespace().any(|a| a == "filter=lfs") {
+            patterns.push(pattern.to_string());
         }
     }
+    patterns
+}
+
+// Parse the output of git config --list -z, where each entry is a key and
+// value separated by newline, terminated by NUL.
+fn parse_config_list(out: &str) -> Vec<(String, String)> {
+    out.split_terminator('\0')
+       .map(|entry| match entry.split_once('\n') {
+           Some((key, val)) => (key.to_string(), val.to_string()),
+           None => (entry.to_string(), String::new()),
+       })
+       .collect()
+}
+
+// Returns true if the error is a git command exiting with 1, which several
+// commands use to answer no.
+fn is_exit_one(e: &failure::Error) -> bool {
+    e.downcast_ref::<GitError>().and_then(GitError::code) == Some(1)
+}
+
+// Send what is read from `src` to `tx` in a background thread, until end of
+// file or the receiver is dropped.
+fn forward_chunks<R: Read + Send + 'static>(mut src: R, tx: mpsc::Sender<Vec<u8>>) {
+    thread::spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            match src.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+// Return the lock used to serialize commands in the repository at `location`.
+fn repository_lock(location: PathBuf) -> Arc<Mutex<()>> {
+    static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+    let mut locks = LOCKS.get_or_init(Default::default)
+                         .lock()
+                         .unwrap_or_else(PoisonError::into_inner);
+    locks.entry(location).or_default().clone()
 }
 
 // -----------------------------------------------------------------------------
@@ -391,4 +2377,276 @@ mod tests {
     fn test_mhgit_unit() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn combined_output() {
+        let mut repo = Repository::new();
+        repo.gitout(GitOut::Combined);
+        let alias = "alias.both=!echo out1; sleep 0.2; echo err >&2; sleep 0.2; echo out2";
+        let out = repo.run(vec!["-c", alias, "both"]).unwrap();
+        assert_eq!(out, "out1\nerr\nout2\n");
+
+        let alias = "alias.fail=!echo out; echo err >&2; exit 3";
+        let err = repo.run(vec!["-c", alias, "fail"]).unwrap_err();
+        let err = err.downcast_ref::<GitError>().unwrap();
+        assert_eq!(err.code(), Some(3));
+        assert_eq!(err.stderr.to_string(), "out\nerr\n");
+    }
+
+    #[test]
+    fn builder() {
+        assert_eq!(RepositoryBuilder::new().build().unwrap(), Repository::new());
+
+        let repo = RepositoryBuilder::new()
+            .location(".")
+            .gitout(GitOut::Print)
+            .quiet(false)
+            .max_output_bytes(1024)
+            .locked(true)
+            .trust()
+            .read_only(true)
+            .editor(true)
+            .dash_c(true)
+            .user_locale(true)
+            .git_binary("/usr/bin/git")
+            .build()
+            .unwrap();
+        let mut want = Repository::at(".").unwrap();
+        want.gitout(GitOut::Print)
+            .quiet(false)
+            .max_output_bytes(1024)
+            .locked(true)
+            .trust()
+            .read_only(true)
+            .editor(true)
+            .dash_c(true)
+            .user_locale(true)
+            .git_binary("/usr/bin/git");
+        assert_eq!(repo, want);
+
+        assert!(RepositoryBuilder::new().location("/no/such/dir").build().is_err());
+    }
+
+    #[test]
+    fn dubious_ownership() {
+        let stderr = "fatal: detected dubious ownership in repository at '/builds/project'
+To add an exception for this directory, call:
+
+\tgit config --global --add safe.directory /builds/project
+";
+        let err = dubious_ownership_error(GitError {
+            cmd: "git status".to_string(),
+            code: Some(128),
+            stdout: String::new(),
+            stderr: format_err!("{}", stderr),
+        }.into());
+        let err = err.downcast_ref::<DubiousOwnershipError>().expect("not classified");
+        assert_eq!(err.path(), "/builds/project");
+        assert_eq!(err.git.code(), Some(128));
+
+        let err = dubious_ownership_error(GitError {
+            cmd: "git status".to_string(),
+            code: Some(128),
+            stdout: String::new(),
+            stderr: format_err!("fatal: not a git repository"),
+        }.into());
+        assert!(err.downcast_ref::<GitError>().is_some());
+
+        let mut repo = Repository::new();
+        assert!(repo.trust().run(vec!["rev-parse", "--git-dir"]).is_ok());
+    }
+
+    #[test]
+    fn subcommand() {
+        assert_eq!(super::subcommand(&["status", "-s"]), "status");
+        assert_eq!(super::subcommand(&["-c", "a.b=c", "-c", "d.e=f", "commit"]), "commit");
+        assert_eq!(super::subcommand(&[]), "");
+    }
+
+    #[test]
+    fn dash_c() {
+        let mut repo = Repository::at("src").unwrap();
+        let src = fs::canonicalize("src").unwrap();
+        let cmd = repo.command(&["status"]).unwrap();
+        assert_eq!(cmd.get_current_dir(), Some(src.as_path()));
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), vec!["status"]);
+
+        repo.dash_c(true);
+        let cmd = repo.command(&["status"]).unwrap();
+        assert_eq!(cmd.get_current_dir(), None);
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), vec!["-C".as_ref(), src.as_os_str(), "status".as_ref()]);
+        assert_eq!(repo.run(vec!["rev-parse", "--show-prefix"]).unwrap(), "src/\n");
+
+        // Without a location the working directory is used either way
+        let cmd = Repository::new().dash_c(true).command(&["status"]).unwrap();
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), vec!["status"]);
+    }
+
+    #[test]
+    fn editor() {
+        let repo = Repository::new();
+        let alias = "alias.editors=!echo $GIT_EDITOR $GIT_SEQUENCE_EDITOR";
+        assert_eq!(repo.run(vec!["-c", alias, "editors"]).unwrap(), "true true\n");
+    }
+
+    #[test]
+    fn user_locale() {
+        let envs = |repo: &Repository| {
+            let cmd = repo.command(&["status"]).unwrap();
+            cmd.get_envs()
+               .filter(|(k, _)| *k == "LC_ALL" || *k == "LANG")
+               .map(|(k, v)| (k.to_owned(), v.map(|v| v.to_owned())))
+               .collect::<Vec<_>>()
+        };
+        let mut repo = Repository::new();
+        assert_eq!(
+            envs(&repo),
+            vec![("LANG".into(), Some("C".into())), ("LC_ALL".into(), Some("C".into()))]
+        );
+        let alias = "alias.locale=!echo $LC_ALL $LANG";
+        assert_eq!(repo.run(vec!["-c", alias, "locale"]).unwrap(), "C C\n");
+
+        repo.user_locale(true);
+        assert!(envs(&repo).is_empty());
+    }
+
+    #[test]
+    fn git_not_found() {
+        let mut repo = Repository::new();
+        repo.git_binary("/no/such/git");
+        let err = repo.status().unwrap_err();
+        assert!(err.downcast_ref::<GitNotFoundError>().is_some(), "unexpected error: {}", err);
+        assert_eq!(
+            err.to_string(),
+            "git executable \"/no/such/git\" not found, make sure git is installed and in PATH"
+        );
+
+        let err = repo.gitout(GitOut::Combined).status().unwrap_err();
+        assert!(err.downcast_ref::<GitNotFoundError>().is_some(), "unexpected error: {}", err);
+
+        let err = crate::commands::CloneOptions::new()
+            .git_binary("/no/such/git")
+            .run("../foo")
+            .unwrap_err();
+        assert!(err.downcast_ref::<GitNotFoundError>().is_some(), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn config_list() {
+        let out = "user.name\nMagnus\x00remote.origin.fetch\n+refs/heads/*:refs/remotes/origin/*\x00\
+                   remote.origin.fetch\n+refs/tags/*:refs/tags/*\x00core.bare\x00alias.multi\nline 1\nline 2\x00";
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            parse_config_list(out),
+            vec![
+                pair("user.name", "Magnus"),
+                pair("remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"),
+                pair("remote.origin.fetch", "+refs/tags/*:refs/tags/*"),
+                pair("core.bare", ""),
+                pair("alias.multi", "line 1\nline 2"),
+            ]
+        );
+        assert!(parse_config_list("").is_empty());
+        assert!(!Repository::new().config_list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_attr() {
+        let out = "assets/logo.png: filter: lfs
+assets/logo.png: diff: unset
+assets/logo.png: export-ignore: unspecified
+dir: name.txt: text: set
+";
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            parse_check_attr(out).unwrap(),
+            vec![
+                pair("filter", "lfs"),
+                pair("diff", "unset"),
+                pair("export-ignore", "unspecified"),
+                pair("text", "set"),
+            ]
+        );
+        assert!(parse_check_attr("").unwrap().is_empty());
+        assert!(parse_check_attr("logo.png filter lfs").is_err());
+    }
+
+    #[test]
+    fn lfs_patterns() {
+        let txt = "# Git LFS
+*.psd filter=lfs diff=lfs merge=lfs -text
+assets/**/*.png  filter=lfs diff=lfs merge=lfs -text
+\"docs/Big File.pdf\" filter=lfs diff=lfs merge=lfs -text
+
+*.rs text eol=lf
+*.zip -filter
+[attr]binary -diff -merge -text
+*.bin filter=other
+";
+        assert_eq!(
+            parse_lfs_patterns(txt),
+            vec!["*.psd", "assets/**/*.png", "docs/Big File.pdf"]
+        );
+        assert!(parse_lfs_patterns("").is_empty());
+    }
+
+    #[test]
+    fn read_only() {
+        let mut repo = Repository::new();
+        repo.read_only(true);
+        assert!(repo.run(vec!["rev-parse", "--git-dir"]).is_ok());
+        assert!(repo.status().is_ok());
+
+        let err = repo.commit("Not allowed").unwrap_err();
+        assert!(err.downcast_ref::<ReadOnlyError>().is_some());
+        assert_eq!(err.to_string(), "git commit is not allowed in a read-only repository");
+        let err = crate::commands::AddOptions::new().run(&repo).unwrap_err();
+        assert!(err.downcast_ref::<ReadOnlyError>().is_some());
+    }
+
+    #[test]
+    fn is_read_only() {
+        for args in [
+            &["status"][..],
+            &["-c", "a.b=c", "log"],
+            &["branch", "--list"],
+            &["config", "--list", "-z"],
+            &["config", "--get", "alias.co"],
+            &["remote", "get-url", "origin"],
+            &["stash", "list"],
+            &["tag", "-v", "v1.0"],
+            &["worktree", "list", "--porcelain"],
+            &["hash-object", "--", "-w"],
+            &["symbolic-ref", "-q", "--short", "HEAD"],
+        ] {
+            assert!(super::is_read_only(args), "{:?} is read-only", args);
+        }
+        for args in [
+            &["commit"][..],
+            &["-c", "a.b=c"],
+            &["branch", "-d", "feature"],
+            &["branch", "feature", "--list"],
+            &["config", "alias.co", "checkout"],
+            &["remote", "add", "origin", "url"],
+            &["stash"],
+            &["stash", "pop"],
+            &["tag", "-m", "-l", "v1.0"],
+            &["worktree", "add", "../hotfix"],
+            &["hash-object", "-w", "--", "foo.txt"],
+            &["symbolic-ref", "HEAD", "refs/heads/feature"],
+            &["symbolic-ref", "-d", "HEAD"],
+        ] {
+            assert!(!super::is_read_only(args), "{:?} modifies", args);
+        }
+    }
+
+    #[test]
+    fn quiet() {
+        let mut repo = Repository::new();
+        assert_eq!(repo.quiet_args(vec!["pull"]), vec!["pull", "-q"]);
+        repo.quiet(false);
+        assert_eq!(repo.quiet_args(vec!["pull"]), vec!["pull"]);
+        repo.quiet(true);
+        assert_eq!(repo.quiet_args(vec!["push"]), vec!["push", "-q"]);
+    }
 }
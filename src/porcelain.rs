@@ -0,0 +1,209 @@
+//! Ref updates parsed from the porcelain output of git push and git fetch.
+
+// Copyright 2020 Magnus Aa. Hirth. All rights reserved.
+
+use failure::Error;
+
+/// Result of updating a single ref, as reported by `git push --porcelain`
+/// and `git fetch --porcelain`.
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use mhgit::{CommandOptions, Repository};
+/// use mhgit::commands::FetchOptions;
+///
+/// let repo = Repository::new();
+/// for update in FetchOptions::new().porcelain(true).run(&repo)? {
+///     println!("{} {} -> {}", update.to, update.old_oid, update.new_oid);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RefUpdate {
+    /// Status flag: ` ` fast-forward, `+` forced update, `-` deleted,
+    /// `*` new ref, `!` rejected and `=` up to date. Fetch also uses `t`
+    /// for updated tags.
+    pub flag: char,
+
+    /// Ref updated from: the local ref of a push, empty for deleted refs
+    /// and fetches
+    pub from: String,
+
+    /// Ref updated: the remote ref of a push, or the local ref of a fetch
+    pub to: String,
+
+    /// Oid before the update, empty if the ref didn't exist or git didn't
+    /// print it. Push prints abbreviated oids.
+    pub old_oid: String,
+
+    /// Oid after the update, empty if the ref was deleted or git didn't
+    /// print it. Push prints abbreviated oids.
+    pub new_oid: String,
+
+    /// Summary of a push, like `[rejected] (non-fast-forward)` or
+    /// `abc123..def456`. Empty for fetches.
+    pub summary: String,
+}
+
+impl RefUpdate {
+    /// True if the ref was rejected by git or the remote.
+    pub fn is_rejected(&self) -> bool {
+        self.flag == '!'
+    }
+}
+
+/// Parse the output of `git push --porcelain`, with a line per ref:
+///
+/// ```text
+/// <flag> TAB <from>:<to> TAB <summary> (<reason>)
+/// ```
+pub(crate) fn parse_push(out: &str) -> Result<Vec<RefUpdate>, Error> {
+    let mut updates = Vec::new();
+    for line in out.lines() {
+        // Skip "To <url>" and "Done" lines
+        if !line.contains('\t') {
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let (flag, refs, summary) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(flag), Some(refs), Some(summary)) => (flag, refs, summary),
+            _ => bail!("bad push porcelain format: {}", line),
+        };
+        let (from, to) = refs
+            .split_once(':')
+            .ok_or_else(|| format_err!("bad push porcelain refs: {}", line))?;
+        // Updated refs have a summary of <old>..<new>, or <old>...<new> for
+        // forced updates
+        let range = summary.split(' ').next().unwrap_or_default();
+        let (old_oid, new_oid) = match range.split_once("...").or_else(|| range.split_once("..")) {
+            Some((old, new)) if !range.starts_with('[') => (old, new),
+            _ => ("", ""),
+        };
+        updates.push(RefUpdate {
+            flag: parse_flag(flag, "push", line)?,
+            from: from.to_string(),
+            to: to.to_string(),
+            old_oid: old_oid.to_string(),
+            new_oid: new_oid.to_string(),
+            summary: summary.to_string(),
+        });
+    }
+    Ok(updates)
+}
+
+/// Parse the output of `git fetch --porcelain`, with a line per ref:
+///
+/// ```text
+/// <flag> SP <old oid> SP <new oid> SP <local ref>
+/// ```
+///
+/// The all-zero oid of missing refs is returned as an empty oid, like for
+/// push.
+pub(crate) fn parse_fetch(out: &str) -> Result<Vec<RefUpdate>, Error> {
+    let oid = |oid: &str| {
+        if oid.chars().all(|c| c == '0') {
+            String::new()
+        } else {
+            oid.to_string()
+        }
+    };
+    let mut updates = Vec::new();
+    for line in out.lines().filter(|l| !l.is_empty()) {
+        // The flag may be a space, so split after it
+        let (flag, rest) = match line.char_indices().nth(1) {
+            Some((i, ' ')) => line.split_at(i),
+            _ => bail!("bad fetch porcelain format: {}", line),
+        };
+        let mut fields = rest[1..].splitn(3, ' ');
+        let (old, new, to) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(old), Some(new), Some(to)) => (old, new, to),
+            _ => bail!("bad fetch porcelain format: {}", line),
+        };
+        updates.push(RefUpdate {
+            flag: parse_flag(flag, "fetch", line)?,
+            to: to.to_string(),
+            old_oid: oid(old),
+            new_oid: oid(new),
+            ..Default::default()
+        });
+    }
+    Ok(updates)
+}
+
+// Parse a single character status flag.
+fn parse_flag(flag: &str, cmd: &str, line: &str) -> Result<char, Error> {
+    match flag.chars().collect::<Vec<_>>()[..] {
+        [c] => Ok(c),
+        _ => bail!("bad {} porcelain flag: {}", cmd, line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push() {
+        let out = "To github.com:MHmorgan/mhgit.git
+ \trefs/heads/master:refs/heads/master\t1a2b3c4..5d6e7f8
++\trefs/heads/topic:refs/heads/topic\t9a8b7c6...5d6e7f8 (forced update)
+*\trefs/heads/dev:refs/heads/dev\t[new branch]
+-\t:refs/heads/old\t[deleted]
+=\trefs/tags/v1.0:refs/tags/v1.0\t[up to date]
+!\trefs/heads/wip:refs/heads/wip\t[rejected] (non-fast-forward)
+Done
+";
+        let updates = parse_push(out).unwrap();
+        let flags: Vec<char> = updates.iter().map(|u| u.flag).collect();
+        assert_eq!(flags, vec![' ', '+', '*', '-', '=', '!']);
+        assert_eq!(
+            updates[0],
+            RefUpdate {
+                flag: ' ',
+                from: "refs/heads/master".to_string(),
+                to: "refs/heads/master".to_string(),
+                old_oid: "1a2b3c4".to_string(),
+                new_oid: "5d6e7f8".to_string(),
+                summary: "1a2b3c4..5d6e7f8".to_string(),
+            }
+        );
+        assert_eq!((updates[1].old_oid.as_str(), updates[1].new_oid.as_str()), ("9a8b7c6", "5d6e7f8"));
+        assert_eq!((updates[2].old_oid.as_str(), updates[2].new_oid.as_str()), ("", ""));
+        assert_eq!(updates[3].from, "");
+        assert!(updates[5].is_rejected() && !updates[0].is_rejected());
+
+        assert!(parse_push("").unwrap().is_empty());
+        assert!(parse_push("??\tfoo\tbar").is_err());
+        assert!(parse_push("!\tfoo").is_err());
+    }
+
+    #[test]
+    fn fetch() {
+        let out = "  0148ecaaf4de7cf72894420df84f5f4b808a88c0 bebde4dfc9d06b00a6081db6aa71e74d21332305 refs/remotes/origin/master
+* 0000000000000000000000000000000000000000 d43fb9b2bd1ad0b6c2f5bbcd4a2a4f6ca1b46b26 refs/remotes/origin/dev
+- 288d723fce8678bcdcb40bfa844a6f815d625661 0000000000000000000000000000000000000000 refs/remotes/origin/old
+t 288d723fce8678bcdcb40bfa844a6f815d625661 e47c0835424019d3cb9f3daf768eafbb2fd42044 refs/tags/v1.0
+";
+        let updates = parse_fetch(out).unwrap();
+        let flags: Vec<char> = updates.iter().map(|u| u.flag).collect();
+        assert_eq!(flags, vec![' ', '*', '-', 't']);
+        assert_eq!(
+            updates[0],
+            RefUpdate {
+                flag: ' ',
+                to: "refs/remotes/origin/master".to_string(),
+                old_oid: "0148ecaaf4de7cf72894420df84f5f4b808a88c0".to_string(),
+                new_oid: "bebde4dfc9d06b00a6081db6aa71e74d21332305".to_string(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(updates[1].old_oid, "");
+        assert_eq!(updates[2].new_oid, "");
+        assert_eq!(updates[3].to, "refs/tags/v1.0");
+
+        assert!(parse_fetch("").unwrap().is_empty());
+        assert!(parse_fetch("* 0000 refs/heads/dev").is_err());
+        assert!(parse_fetch("** 0000 1111 refs/heads/dev").is_err());
+    }
+}
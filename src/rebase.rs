@@ -0,0 +1,150 @@
+//! Rebase state read from the git directory.
+
+// Copyright 2020 Magnus Aa. Hirth. All rights reserved.
+
+use failure::{Error, ResultExt};
+use std::fs;
+use std::path::Path;
+
+/// State of a rebase in progress, as returned by
+/// [`Repository::rebase_status`].
+///
+/// ```rust,no_run
+/// use mhgit::Repository;
+///
+/// fn main() {
+///     if let Some(rebase) = Repository::new().rebase_status().unwrap() {
+///         println!("rebasing onto {}: {}/{}", rebase.onto, rebase.step, rebase.total);
+///     }
+/// }
+/// ```
+///
+/// [`Repository::rebase_status`]: struct.Repository.html#method.rebase_status
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RebaseStatus {
+    /// Oid of the commit being rebased onto
+    pub onto: String,
+
+    /// Number of the current step, starting at 1
+    pub step: usize,
+
+    /// Total number of steps
+    pub total: usize,
+
+    /// The branch being rebased, like `refs/heads/feature`, or `None` if
+    /// rebasing a detached HEAD
+    pub head_name: Option<String>,
+
+    /// True if the rebase is interactive
+    pub interactive: bool,
+}
+
+impl RebaseStatus {
+    /// Read the rebase state in `git_dir`, or `None` if no rebase is in
+    /// progress.
+    ///
+    /// Merge based rebases keep their state in `rebase-merge`, and apply
+    /// based rebases in `rebase-apply`. `git am` also uses `rebase-apply`,
+    /// which is not a rebase.
+    pub(crate) fn read(git_dir: &Path) -> Result<Option<RebaseStatus>, Error> {
+        let merge_dir = git_dir.join("rebase-merge");
+        let apply_dir = git_dir.join("rebase-apply");
+        let (dir, step_file, total_file) = if merge_dir.is_dir() {
+            (merge_dir, "msgnum", "end")
+        } else if apply_dir.join("rebasing").exists() {
+            (apply_dir, "next", "last")
+        } else {
+            return Ok(None);
+        };
+
+        let read = |name: &str| -> Result<String, Error> {
+            let path = dir.join(name);
+            let txt = fs::read_to_string(&path)
+                .with_context(|_| format!("failed to read {}", path.display()))?;
+            Ok(txt.trim_end().to_string())
+        };
+        let number = |name: &str| -> Result<usize, Error> {
+            let txt = read(name)?;
+            Ok(txt.parse::<usize>().with_context(|_| format!("bad rebase {}: {}", name, txt))?)
+        };
+        let head_name = read("head-name")?;
+        Ok(Some(RebaseStatus {
+            onto: read("onto")?,
+            step: number(step_file)?,
+            total: number(total_file)?,
+            head_name: if head_name == "detached HEAD" { None } else { Some(head_name) },
+            interactive: dir.join("interactive").exists(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    // Create an empty git dir for a test.
+    fn git_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("mhgit-rebase-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        fs::create_dir_all(dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn rebase_merge() {
+        let dir = git_dir("merge");
+        assert_eq!(RebaseStatus::read(&dir).unwrap(), None);
+
+        write(&dir.join("rebase-merge"), &[
+            ("onto", "0148ecaaf4de7cf72894420df84f5f4b808a88c0\n"),
+            ("msgnum", "2\n"),
+            ("end", "5\n"),
+            ("head-name", "refs/heads/feature\n"),
+            ("interactive", ""),
+        ]);
+        assert_eq!(
+            RebaseStatus::read(&dir).unwrap(),
+            Some(RebaseStatus {
+                onto: "0148ecaaf4de7cf72894420df84f5f4b808a88c0".to_string(),
+                step: 2,
+                total: 5,
+                head_name: Some("refs/heads/feature".to_string()),
+                interactive: true,
+            })
+        );
+
+        fs::write(dir.join("rebase-merge/msgnum"), "two\n").unwrap();
+        assert!(RebaseStatus::read(&dir).is_err());
+        fs::remove_file(dir.join("rebase-merge/end")).unwrap();
+        assert!(RebaseStatus::read(&dir).is_err());
+    }
+
+    #[test]
+    fn rebase_apply() {
+        let dir = git_dir("apply");
+        write(&dir.join("rebase-apply"), &[
+            ("onto", "0148ecaaf4de7cf72894420df84f5f4b808a88c0\n"),
+            ("next", "1\n"),
+            ("last", "3\n"),
+            ("head-name", "detached HEAD\n"),
+        ]);
+        // Applying patches with git am
+        assert_eq!(RebaseStatus::read(&dir).unwrap(), None);
+
+        write(&dir.join("rebase-apply"), &[("rebasing", "")]);
+        let rebase = RebaseStatus::read(&dir).unwrap().unwrap();
+        assert_eq!((rebase.step, rebase.total), (1, 3));
+        assert_eq!(rebase.head_name, None);
+        assert!(!rebase.interactive);
+    }
+}
@@ -0,0 +1,265 @@
+//! Remote types returned from git remote show.
+
+// Copyright 2020 Magnus Aa. Hirth. All rights reserved.
+
+use failure::Error;
+use std::convert::TryFrom;
+
+/// Information about a remote, as reported by `git remote show`.
+///
+/// ```rust,no_run
+/// use mhgit::Repository;
+///
+/// fn main() {
+///     let info = Repository::new().remote_info("origin").unwrap();
+///     for r in &info.push_refs {
+///         println!("{} -> {}: {}", r.local, r.remote, r.status);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RemoteInfo {
+    /// Name of the remote
+    pub name: String,
+
+    /// URL fetched from
+    pub fetch_url: String,
+
+    /// URL pushed to
+    pub push_url: String,
+
+    /// Branch the remote HEAD points to, if known
+    pub head_branch: Option<String>,
+
+    /// Branches on the remote
+    pub remote_branches: Vec<RemoteBranch>,
+
+    /// Local branches configured for `git pull`
+    pub pull_branches: Vec<PullBranch>,
+
+    /// Local refs configured for `git push`
+    pub push_refs: Vec<PushRef>,
+}
+
+/// A branch on the remote.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RemoteBranch {
+    /// Branch name on the remote
+    pub name: String,
+
+    /// Tracking state: `tracked`, `new (next fetch will store in remotes/origin)`, etc.
+    pub state: String,
+}
+
+/// A local branch configured for `git pull`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PullBranch {
+    /// Local branch name
+    pub local: String,
+
+    /// Remote branch merged or rebased onto
+    pub remote: String,
+
+    /// True if the local branch is rebased rather than merged
+    pub rebase: bool,
+}
+
+/// A local ref configured for `git push`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PushRef {
+    /// Local ref name
+    pub local: String,
+
+    /// Remote ref pushed to
+    pub remote: String,
+
+    /// True if the ref is force pushed
+    pub force: bool,
+
+    /// Push state: `up to date`, `fast-forwardable`, `local out of date`, etc.
+    /// Empty if the remote wasn't queried.
+    pub status: String,
+}
+
+impl TryFrom<&str> for RemoteInfo {
+    type Error = Error;
+
+    /// Parse captured output text from git remote show
+    fn try_from(txt: &str) -> std::result::Result<RemoteInfo, Self::Error> {
+        #[derive(PartialEq)]
+        enum Section {
+            None,
+            Remote,
+            Pull,
+            Push,
+        }
+        let mut info = RemoteInfo { ..Default::default() };
+        let mut section = Section::None;
+
+        for line in txt.lines() {
+            if let Some(name) = line.strip_prefix("* remote ") {
+                info.name = name.trim().to_string();
+                continue;
+            }
+
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Section items
+            if indent > 2 {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match section {
+                    Section::Remote => info.remote_branches.push(RemoteBranch {
+                        name: words[0].to_string(),
+                        state: words[1..].join(" "),
+                    }),
+                    // <local> merges with remote <remote>
+                    // <local> rebases onto remote <remote>
+                    Section::Pull if words.len() >= 4 && words[0] != "and" => {
+                        info.pull_branches.push(PullBranch {
+                            local: words[0].to_string(),
+                            remote: words[words.len() - 1].to_string(),
+                            rebase: words[1] == "rebases",
+                        })
+                    }
+                    // <local> pushes to <remote> (<status>)
+                    // <local> forces to <remote> (<status>)
+                    Section::Push if words.len() >= 4 => {
+                        let status = match line.rfind('(') {
+                            Some(i) => line[i + 1..].trim_end_matches(')').to_string(),
+                            None => String::new(),
+                        };
+                        info.push_refs.push(PushRef {
+                            local: words[0].to_string(),
+                            remote: words[3].to_string(),
+                            force: words[1] == "forces",
+                            status,
+                        })
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+
+            // Key-value lines and section headers
+            let (key, val) = match line.split_once(':') {
+                Some((key, val)) => (key.split_whitespace().collect::<Vec<_>>().join(" "), val.trim()),
+                None => bail!("bad remote show format: {}", line),
+            };
+            section = Section::None;
+            match key.as_str() {
+                "Fetch URL" => info.fetch_url = val.to_string(),
+                "Push URL" => info.push_url = val.to_string(),
+                "HEAD branch" if val != "(unknown)" => info.head_branch = Some(val.to_string()),
+                "Remote branch" | "Remote branches" => section = Section::Remote,
+                _ if key.starts_with("Local branch") => section = Section::Pull,
+                _ if key.starts_with("Local ref") => section = Section::Push,
+                _ => (),
+            }
+        }
+
+        ensure!(!info.name.is_empty(), "bad remote show format: missing remote name");
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static OUT: &str = "* remote origin
+  Fetch URL: https://github.com/MHmorgan/mhgit.git
+  Push  URL: git@github.com:MHmorgan/mhgit.git
+  HEAD branch: master
+  Remote branches:
+    dev    tracked
+    master tracked
+    next   new (next fetch will store in remotes/origin)
+    old    stale (use 'git remote prune' to remove)
+  Local branches configured for 'git pull':
+    dev    rebases onto remote dev
+    master merges with remote master
+  Local refs configured for 'git push':
+    dev    pushes to dev    (fast-forwardable)
+    master pushes to master (up to date)
+    wip    forces to wip    (local out of date)
+";
+
+    #[test]
+    fn remote_info() {
+        let info = RemoteInfo::try_from(OUT).expect("failed to parse remote show output");
+        let want = RemoteInfo {
+            name: String::from("origin"),
+            fetch_url: String::from("https://github.com/MHmorgan/mhgit.git"),
+            push_url: String::from("git@github.com:MHmorgan/mhgit.git"),
+            head_branch: Some(String::from("master")),
+            remote_branches: vec![
+                RemoteBranch { name: String::from("dev"), state: String::from("tracked") },
+                RemoteBranch { name: String::from("master"), state: String::from("tracked") },
+                RemoteBranch {
+                    name: String::from("next"),
+                    state: String::from("new (next fetch will store in remotes/origin)"),
+                },
+                RemoteBranch {
+                    name: String::from("old"),
+                    state: String::from("stale (use 'git remote prune' to remove)"),
+                },
+            ],
+            pull_branches: vec![
+                PullBranch { local: String::from("dev"), remote: String::from("dev"), rebase: true },
+                PullBranch { local: String::from("master"), remote: String::from("master"), rebase: false },
+            ],
+            push_refs: vec![
+                PushRef {
+                    local: String::from("dev"),
+                    remote: String::from("dev"),
+                    force: false,
+                    status: String::from("fast-forwardable"),
+                },
+                PushRef {
+                    local: String::from("master"),
+                    remote: String::from("master"),
+                    force: false,
+                    status: String::from("up to date"),
+                },
+                PushRef {
+                    local: String::from("wip"),
+                    remote: String::from("wip"),
+                    force: true,
+                    status: String::from("local out of date"),
+                },
+            ],
+        };
+        assert_eq!(info, want, "Remote info not parsed correctly");
+    }
+
+    #[test]
+    fn remote_info_single() {
+        let out = "* remote upstream
+  Fetch URL: ../upstream
+  Push  URL: ../upstream
+  HEAD branch: (unknown)
+  Remote branch:
+    main tracked
+  Local branch configured for 'git pull':
+    main merges with remote main
+  Local ref configured for 'git push':
+    main pushes to main (up to date)
+";
+        let info = RemoteInfo::try_from(out).expect("failed to parse remote show output");
+        assert_eq!(info.name, "upstream");
+        assert_eq!(info.head_branch, None);
+        assert_eq!(info.remote_branches.len(), 1);
+        assert_eq!(info.pull_branches.len(), 1);
+        assert_eq!(info.push_refs[0].status, "up to date");
+    }
+
+    #[test]
+    fn remote_info_invalid() {
+        assert!(RemoteInfo::try_from("").is_err());
+        assert!(RemoteInfo::try_from("* remote origin\n  Fetch URL ../foo\n").is_err());
+    }
+}
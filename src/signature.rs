@@ -0,0 +1,112 @@
+//! Signature types returned from git verify-commit.
+
+// Copyright 2020 Magnus Aa. Hirth. All rights reserved.
+
+/// Result of verifying the GPG signature of a commit, as returned by
+/// [`Repository::verify_commit`].
+///
+/// ```rust,no_run
+/// use mhgit::Repository;
+///
+/// fn main() {
+///     let sig = Repository::new().verify_commit("HEAD").unwrap();
+///     if sig.valid {
+///         println!("signed by {} ({})", sig.signer, sig.key_id);
+///     }
+/// }
+/// ```
+///
+/// [`Repository::verify_commit`]: struct.Repository.html#method.verify_commit
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SignatureStatus {
+    /// True if the commit has a good signature
+    pub valid: bool,
+
+    /// User id of the signing key, like `name <email>`. Empty if the commit
+    /// isn't signed or the key is unknown.
+    pub signer: String,
+
+    /// Long id of the signing key. Empty if the commit isn't signed.
+    pub key_id: String,
+}
+
+impl SignatureStatus {
+    /// Parse the GPG status lines printed by `git verify-commit --raw`.
+    /// `success` is true if git exited successfully.
+    pub(crate) fn from_raw(raw: &str, success: bool) -> SignatureStatus {
+        let mut sig = SignatureStatus::default();
+        let mut good = false;
+        for line in raw.lines() {
+            let mut words = match line.strip_prefix("[GNUPG:] ") {
+                Some(status) => status.splitn(3, ' '),
+                None => continue,
+            };
+            let status = words.next().unwrap_or("");
+            match status {
+                "GOODSIG" => good = true,
+                "BADSIG" | "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" | "ERRSIG" => good = false,
+                _ => continue,
+            }
+            sig.key_id = words.next().unwrap_or("").to_string();
+            // ERRSIG is followed by key details rather than a user id
+            sig.signer = match status {
+                "ERRSIG" => String::new(),
+                _ => words.next().unwrap_or("").to_string(),
+            };
+        }
+        sig.valid = good && success;
+        sig
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn good_signature() {
+        let raw = "[GNUPG:] NEWSIG
+[GNUPG:] KEY_CONSIDERED 6E9C4A2B1F0D8E7C3B5A49281706F5E4D3C2B1A0 0
+[GNUPG:] SIG_ID kD1y0XZ0v0pWq2h0Yw2x5s0Z9mA 2020-06-13 1592042400
+[GNUPG:] GOODSIG 1706F5E4D3C2B1A0 Magnus Hirth <magnus@web.com>
+[GNUPG:] VALIDSIG 6E9C4A2B1F0D8E7C3B5A49281706F5E4D3C2B1A0 2020-06-13 1592042400 0 4 0 1 10 00 6E9C4A2B1F0D8E7C3B5A49281706F5E4D3C2B1A0
+[GNUPG:] TRUST_ULTIMATE 0 pgp
+";
+        assert_eq!(
+            SignatureStatus::from_raw(raw, true),
+            SignatureStatus {
+                valid: true,
+                signer: "Magnus Hirth <magnus@web.com>".to_string(),
+                key_id: "1706F5E4D3C2B1A0".to_string(),
+            }
+        );
+        // Git may still reject the signature, like for low trust
+        assert!(!SignatureStatus::from_raw(raw, false).valid);
+    }
+
+    #[test]
+    fn bad_signature() {
+        let raw = "[GNUPG:] NEWSIG
+[GNUPG:] BADSIG 1706F5E4D3C2B1A0 Magnus Hirth <magnus@web.com>
+";
+        let sig = SignatureStatus::from_raw(raw, false);
+        assert!(!sig.valid);
+        assert_eq!(sig.signer, "Magnus Hirth <magnus@web.com>");
+        assert_eq!(sig.key_id, "1706F5E4D3C2B1A0");
+
+        let raw = "[GNUPG:] NEWSIG
+[GNUPG:] ERRSIG 1706F5E4D3C2B1A0 1 10 00 1592042400 9 -
+[GNUPG:] NO_PUBKEY 1706F5E4D3C2B1A0
+";
+        let sig = SignatureStatus::from_raw(raw, false);
+        assert!(!sig.valid);
+        assert_eq!(sig.signer, "");
+        assert_eq!(sig.key_id, "1706F5E4D3C2B1A0");
+    }
+
+    #[test]
+    fn unsigned() {
+        assert_eq!(SignatureStatus::from_raw("", false), SignatureStatus::default());
+        assert!(!SignatureStatus::from_raw("error: no signature found\n", true).valid);
+    }
+}
@@ -0,0 +1,152 @@
+//! Repository state summarizing status and operations in progress.
+
+// Copyright 2020 Magnus Aa. Hirth. All rights reserved.
+
+use crate::Status;
+use std::path::Path;
+
+/// Operation in progress in a repository, which must be finished or aborted
+/// before starting another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// A merge stopped at conflicts
+    Merge,
+    /// A rebase, see [`Repository::rebase_status`] for details
+    ///
+    /// [`Repository::rebase_status`]: struct.Repository.html#method.rebase_status
+    Rebase,
+    /// A cherry-pick stopped at conflicts
+    CherryPick,
+}
+
+impl Operation {
+    /// Return the operation in progress in `git_dir`, if any. A rebase
+    /// stopping at a conflicted pick is a rebase, not a cherry-pick.
+    pub(crate) fn read(git_dir: &Path) -> Option<Operation> {
+        if is_rebasing(git_dir) {
+            Some(Operation::Rebase)
+        } else if is_merging(git_dir) {
+            Some(Operation::Merge)
+        } else if is_cherry_picking(git_dir) {
+            Some(Operation::CherryPick)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn is_merging(git_dir: &Path) -> bool {
+    git_dir.join("MERGE_HEAD").exists()
+}
+
+pub(crate) fn is_rebasing(git_dir: &Path) -> bool {
+    // rebase-apply without rebasing is used by git am
+    git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply/rebasing").exists()
+}
+
+pub(crate) fn is_cherry_picking(git_dir: &Path) -> bool {
+    git_dir.join("CHERRY_PICK_HEAD").exists()
+}
+
+/// Summary of the state of a repository, as returned by
+/// [`Repository::state`]. Ahead and behind counts are of the current branch
+/// compared to its upstream.
+///
+/// ```rust,no_run
+/// use mhgit::Repository;
+///
+/// fn main() {
+///     let state = Repository::new().state().unwrap();
+///     let branch = state.branch.as_deref().unwrap_or("HEAD");
+///     let dirty = if state.dirty { "*" } else { "" };
+///     println!("{}{} ↑{} ↓{}", branch, dirty, state.ahead, state.behind);
+/// }
+/// ```
+///
+/// [`Repository::state`]: struct.Repository.html#method.state
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RepoState {
+    /// Current branch, or `None` if HEAD is detached
+    pub branch: Option<String>,
+
+    /// Oid of the current commit, empty if there are no commits yet
+    pub oid: String,
+
+    /// Upstream branch of the current branch, if set
+    pub upstream: Option<String>,
+
+    /// Number of commits the branch is ahead of upstream, 0 without upstream
+    pub ahead: u32,
+
+    /// Number of commits the branch is behind upstream, 0 without upstream
+    pub behind: u32,
+
+    /// True if tracked files have staged, unstaged or unmerged changes
+    pub dirty: bool,
+
+    /// True if there are untracked files
+    pub untracked: bool,
+
+    /// Operation in progress, if any
+    pub operation: Option<Operation>,
+}
+
+impl RepoState {
+    pub(crate) fn new(status: &Status, operation: Option<Operation>) -> RepoState {
+        let branch = match status.branch_head() {
+            "" => None,
+            _ if status.is_detached() => None,
+            head => Some(head.to_string()),
+        };
+        let oid = match status.branch_oid() {
+            "(initial)" => "",
+            oid => oid,
+        };
+        RepoState {
+            branch,
+            oid: oid.to_string(),
+            upstream: status.upstream_branch().map(String::from),
+            // Status counts are seen from the upstream
+            ahead: status.upstream_behind().unwrap_or(0),
+            behind: status.upstream_ahead().unwrap_or(0),
+            dirty: status.iter().next().is_some(),
+            untracked: !status.untracked.is_empty(),
+            operation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn from_status() {
+        let status = Status::try_from(
+            "# branch.oid 8e1f7a6c5b4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f
+# branch.head master
+# branch.upstream origin/master
+# branch.ab +2 -1
+? new.txt
+",
+        )
+        .unwrap();
+        assert_eq!(
+            RepoState::new(&status, Some(Operation::Merge)),
+            RepoState {
+                branch: Some("master".to_string()),
+                oid: "8e1f7a6c5b4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f".to_string(),
+                upstream: Some("origin/master".to_string()),
+                ahead: 2,
+                behind: 1,
+                dirty: false,
+                untracked: true,
+                operation: Some(Operation::Merge),
+            }
+        );
+
+        let status = Status::try_from("# branch.oid (initial)\n# branch.head (detached)\n").unwrap();
+        assert_eq!(RepoState::new(&status, None), RepoState::default());
+    }
+}
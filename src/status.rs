@@ -5,8 +5,39 @@
 use crate::GitError;
 use failure::{Error, ResultExt};
 use itertools::Itertools;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 
+/// Status parse errors are returned when the output of git status can't be
+/// parsed, as opposed to [`GitError`] which is returned when git itself fails.
+///
+/// [`GitError`]: struct.GitError.html
+#[derive(Fail, Debug)]
+#[fail(display = "{}: {}", reason, line)]
+pub struct StatusParseError {
+    line: String,
+    reason: String,
+}
+
+impl StatusParseError {
+    fn new(line: &str, reason: &str) -> StatusParseError {
+        StatusParseError {
+            line: line.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The line which couldn't be parsed.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Why the line couldn't be parsed.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
 /// Git status data.
 ///
 /// ```rust,no_run
@@ -35,6 +66,9 @@ pub struct Status {
     /// Renamed/copied entries
     pub renamed: Vec<Entry>,
 
+    /// Unmerged entries
+    pub unmerged: Vec<Entry>,
+
     /// Untracked filenames
     pub untracked: Vec<String>,
 
@@ -42,6 +76,57 @@ pub struct Status {
     pub ignored: Vec<String>,
 }
 
+/// Paths added to and removed from the sets of a [`Status`], as returned by
+/// [`Status::diff`].
+///
+/// [`Status`]: struct.Status.html
+/// [`Status::diff`]: struct.Status.html#method.diff
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StatusDelta {
+    /// Changed, renamed/copied and unmerged entry paths
+    pub changed: PathDelta,
+
+    /// Untracked filenames
+    pub untracked: PathDelta,
+
+    /// Ignored filenames
+    pub ignored: PathDelta,
+}
+
+/// Paths added to and removed from a set of paths.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PathDelta {
+    /// Paths only in the new set
+    pub added: Vec<String>,
+
+    /// Paths only in the old set
+    pub removed: Vec<String>,
+}
+
+impl StatusDelta {
+    /// Returns true if no paths were added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.untracked.is_empty() && self.ignored.is_empty()
+    }
+}
+
+impl PathDelta {
+    // Compare two sets of paths, keeping the order of each.
+    fn new(old: &[&str], new: &[&str]) -> PathDelta {
+        let old_set: HashSet<&str> = old.iter().copied().collect();
+        let new_set: HashSet<&str> = new.iter().copied().collect();
+        PathDelta {
+            added: new.iter().filter(|p| !old_set.contains(*p)).map(|p| p.to_string()).collect(),
+            removed: old.iter().filter(|p| !new_set.contains(*p)).map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// Returns true if no paths were added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 /// A single entry from git status output.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Entry {
@@ -82,6 +167,9 @@ pub struct Entry {
     // The pathname. In a renamed/copied entry, this is the target path.
     path: String,
 
+    // The pathname as quoted by git, only if it was quoted
+    raw_path: String,
+
     // Rename/copy score (only renamed/copied entries)
     //  0: R - rename, C - copy
     //  1: similarity percentage
@@ -90,6 +178,9 @@ pub struct Entry {
     // The pathname in the commit at HEAD or in the index (only renamed/copied entries)
     orig_path: String,
 
+    // The original pathname as quoted by git, only if it was quoted
+    raw_orig_path: String,
+
     /// Unmerged entry stages (only unmerged entries)
     ///  0: object name
     ///  1: file mode
@@ -117,6 +208,12 @@ impl Status {
         &self.branch.1
     }
 
+    /// True if HEAD is detached, not on a branch.
+    #[inline]
+    pub fn is_detached(&self) -> bool {
+        self.branch.1 == "(detached)"
+    }
+
     /// Upstream branch, if set
     pub fn upstream_branch(&self) -> Option<&str> {
         if !self.upstream.0.is_empty() {
@@ -143,6 +240,112 @@ impl Status {
             None
         }
     }
+
+    /// Iterate over all changed, renamed/copied and unmerged entries.
+    /// 
+    /// Untracked and ignored files are only paths, found in [`untracked`]
+    /// and [`ignored`].
+    /// 
+    /// [`untracked`]: #structfield.untracked
+    /// [`ignored`]: #structfield.ignored
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.changed.iter()
+            .chain(self.renamed.iter())
+            .chain(self.unmerged.iter())
+    }
+
+    /// Return `(orig_path, path, similarity)` of each renamed entry.
+    /// 
+    /// Copied entries are not included.
+    /// 
+    /// ```rust,no_run
+    /// use mhgit::Repository;
+    ///
+    /// fn main() {
+    ///     for (from, to, score) in Repository::new().status().unwrap().renames() {
+    ///         println!("{} -> {} ({}%)", from, to, score);
+    ///     }
+    /// }
+    /// ```
+    pub fn renames(&self) -> Vec<(String, String, u8)> {
+        self.renamed.iter()
+            .filter(|e| e.is_renamed())
+            .map(|e| (e.orig_path.clone(), e.path.clone(), e.score()))
+            .collect()
+    }
+
+    /// Compare with a newer status, returning the paths added to and removed
+    /// from the changed, untracked and ignored sets.
+    /// 
+    /// Only membership is compared: an entry changing from staged to
+    /// unstaged is in the changed set of both statuses, so it's not reported.
+    /// 
+    /// ```rust,no_run
+    /// use mhgit::Repository;
+    ///
+    /// fn main() {
+    ///     let repo = Repository::new();
+    ///     let before = repo.status().unwrap();
+    ///     // ...
+    ///     let delta = before.diff(&repo.status().unwrap());
+    ///     for path in &delta.untracked.added {
+    ///         println!("new file: {}", path);
+    ///     }
+    /// }
+    /// ```
+    pub fn diff(&self, other: &Status) -> StatusDelta {
+        fn paths(v: &[String]) -> Vec<&str> {
+            v.iter().map(String::as_str).collect()
+        }
+        let changed: Vec<_> = self.iter().map(Entry::pathname).collect();
+        let other_changed: Vec<_> = other.iter().map(Entry::pathname).collect();
+        StatusDelta {
+            changed: PathDelta::new(&changed, &other_changed),
+            untracked: PathDelta::new(&paths(&self.untracked), &paths(&other.untracked)),
+            ignored: PathDelta::new(&paths(&self.ignored), &paths(&other.ignored)),
+        }
+    }
+
+    /// Return the status as `git status --porcelain=v2 --branch` output.
+    /// 
+    /// Parsing the text gives back an equal status. Entries are grouped by
+    /// kind, rather than sorted by path like git does:
+    /// 
+    /// ```text
+    /// # branch.oid <commit>
+    /// # branch.head <branch>
+    /// # branch.upstream <upstream_branch>
+    /// # branch.ab +<ahead> -<behind>
+    /// 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+    /// 2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path><tab><origPath>
+    /// u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+    /// ? <path>
+    /// ! <path>
+    /// ```
+    pub fn to_porcelain_v2(&self) -> String {
+        let mut out = String::new();
+        if !self.branch.0.is_empty() {
+            out += &format!("# branch.oid {}\n", self.branch.0);
+        }
+        if !self.branch.1.is_empty() {
+            out += &format!("# branch.head {}\n", self.branch.1);
+        }
+        if !self.upstream.0.is_empty() {
+            out += &format!("# branch.upstream {}\n", self.upstream.0);
+            out += &format!("# branch.ab +{} -{}\n", self.upstream.1, self.upstream.2);
+        }
+        for entry in self.iter() {
+            out += &entry.to_porcelain_v2();
+            out.push('\n');
+        }
+        for path in &self.untracked {
+            out += &format!("? {}\n", path);
+        }
+        for path in &self.ignored {
+            out += &format!("! {}\n", path);
+        }
+        out
+    }
 }
 
 impl TryFrom<&str> for Status {
@@ -150,15 +353,18 @@ impl TryFrom<&str> for Status {
 
     /// Parse captured output text from git status
     fn try_from(txt: &str) -> std::result::Result<Status, Self::Error> {
-        macro_rules! err {
-            () => {
-                format_err!("bad status format")
-            };
-        };
         let mut status = Status { ..Default::default() };
 
         // Parse entries line by line
         for line in txt.lines() {
+            macro_rules! err {
+                () => {
+                    err!("bad status format")
+                };
+                ($reason:expr) => {
+                    Error::from(StatusParseError::new(line, $reason))
+                };
+            }
             let mut chars = line.chars();
             match chars.next() {
                 // Branch info entry
@@ -171,15 +377,19 @@ impl TryFrom<&str> for Status {
                         "branch.upstream" => status.upstream.0 = chars.take_while(|c| !c.is_whitespace()).collect(),
                         "branch.ab" => {
                             // Branch ahead
-                            ensure!(Some('+') == chars.next(), err!());
+                            if Some('+') != chars.next() {
+                                return Err(err!());
+                            }
                             let tmp: String = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
-                            status.upstream.1 = tmp.parse::<u32>().context(err!())?;
+                            status.upstream.1 = tmp.parse::<u32>().map_err(|_| err!())?;
                             // Branch behind
-                            ensure!(Some('-') == chars.next(), err!());
+                            if Some('-') != chars.next() {
+                                return Err(err!());
+                            }
                             let tmp: String = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
-                            status.upstream.2 = tmp.parse::<u32>().context(err!())?;
+                            status.upstream.2 = tmp.parse::<u32>().map_err(|_| err!())?;
                         },
-                        _ => bail!("unknown branch info {}", line),
+                        _ => return Err(err!("unknown branch info")),
                     }
                 }
                 // Changed entry
@@ -190,20 +400,20 @@ impl TryFrom<&str> for Status {
                 Some('2') => {
                     status.renamed.push(Entry::try_from(line)?)
                 }
-                // Untracked entry
+                // Unmerged entry
+                Some('u') => {
+                    status.unmerged.push(Entry::try_from(line)?)
+                }
+                // Untracked entry, keeping only the decoded path
                 Some('?') => {
-                    let _ = chars.next().ok_or(err!())?;
-                    let path: String = chars.take_while(|c| !c.is_whitespace()).collect();
-                    status.untracked.push(path);
+                    status.untracked.push(Entry::try_from(line)?.path)
                 }
-                // Ignored entry
+                // Ignored entry, keeping only the decoded path
                 Some('!') => {
-                    let _ = chars.next().ok_or(err!())?;
-                    let path: String = chars.take_while(|c| !c.is_whitespace()).collect();
-                    status.ignored.push(path);
+                    status.ignored.push(Entry::try_from(line)?.path)
                 }
                 // Unknown line prefix
-                Some(_) => bail!("unknown line prefix in: {}", line),
+                Some(_) => return Err(err!("unknown line prefix")),
                 // Ignore empty lines
                 None => (),
             }
@@ -272,6 +482,22 @@ impl Entry {
         self.status
     }
 
+    /// Return the two character short status code of the entry, as printed
+    /// by `git status --short`.
+    /// 
+    /// Unmodified states are shown as a space, untracked entries as `??`
+    /// and ignored entries as `!!`.
+    pub fn short_code(&self) -> String {
+        match self.format {
+            '?' => String::from("??"),
+            '!' => String::from("!!"),
+            _ => {
+                let code = |c: char| if c == '.' { ' ' } else { c };
+                [code(self.status.0), code(self.status.1)].iter().collect()
+            }
+        }
+    }
+
     /// Return true if entry is a submodule
     #[inline]
     pub fn is_submodule(&self) -> bool {
@@ -314,6 +540,24 @@ impl Entry {
         &self.file_mode.2
     }
 
+    /// File mode in HEAD as a number, or `None` if not set.
+    #[inline]
+    pub fn file_mode_head_octal(&self) -> Option<u32> {
+        parse_file_mode(&self.file_mode.0)
+    }
+
+    /// File mode in the index as a number, or `None` if not set.
+    #[inline]
+    pub fn file_mode_index_octal(&self) -> Option<u32> {
+        parse_file_mode(&self.file_mode.1)
+    }
+
+    /// File mode in the worktree as a number, or `None` if not set.
+    #[inline]
+    pub fn file_mode_worktree_octal(&self) -> Option<u32> {
+        parse_file_mode(&self.file_mode.2)
+    }
+
     /// Object name (oid) in HEAD.
     #[inline]
     pub fn object_name_head(&self) -> &str {
@@ -327,11 +571,24 @@ impl Entry {
     }
 
     /// Pathname. In a renamed/copied entry, this is the target path.
+    /// 
+    /// Paths quoted by git, like paths with non-ASCII characters, are decoded.
     #[inline]
     pub fn pathname(&self) -> &str {
         &self.path
     }
 
+    /// Pathname exactly as printed by git, including quotes and escapes
+    /// if git quoted it. Use this to pass the path back to git verbatim.
+    #[inline]
+    pub fn raw_pathname(&self) -> &str {
+        if self.raw_path.is_empty() {
+            &self.path
+        } else {
+            &self.raw_path
+        }
+    }
+
     /// Return the score denoting the percentage of similarity between the
     /// source and target of the move or copy. 
     /// 
@@ -348,6 +605,114 @@ impl Entry {
     pub fn orig_path(&self) -> &str {
         &self.orig_path
     }
+
+    /// Return the entry as a line of `git status --porcelain=v2` output,
+    /// without a trailing newline.
+    pub fn to_porcelain_v2(&self) -> String {
+        let mode = |m: &[char; 6]| m.iter().collect::<String>();
+        let sub = if self.sub.0 {
+            [
+                'S',
+                if self.sub.1 { 'C' } else { '.' },
+                if self.sub.2 { 'M' } else { '.' },
+                if self.sub.3 { 'U' } else { '.' },
+            ].iter().collect()
+        } else {
+            String::from("N...")
+        };
+        let prefix = format!("{} {}{} {}", self.format, self.status.0, self.status.1, sub);
+        let raw_orig_path = if self.raw_orig_path.is_empty() {
+            &self.orig_path
+        } else {
+            &self.raw_orig_path
+        };
+        match self.format {
+            '1' => format!(
+                "{} {} {} {} {} {} {}",
+                prefix,
+                mode(&self.file_mode.0),
+                mode(&self.file_mode.1),
+                mode(&self.file_mode.2),
+                self.object_name.0,
+                self.object_name.1,
+                self.raw_pathname()
+            ),
+            '2' => format!(
+                "{} {} {} {} {} {} {}{} {}\t{}",
+                prefix,
+                mode(&self.file_mode.0),
+                mode(&self.file_mode.1),
+                mode(&self.file_mode.2),
+                self.object_name.0,
+                self.object_name.1,
+                self.score.0,
+                self.score.1,
+                self.raw_pathname(),
+                raw_orig_path
+            ),
+            'u' => format!(
+                "{} {} {} {} {} {} {} {} {}",
+                prefix,
+                mode(&self.stage1.1),
+                mode(&self.stage2.1),
+                mode(&self.stage3.1),
+                mode(&self.file_mode.2),
+                self.stage1.0,
+                self.stage2.0,
+                self.stage3.0,
+                self.raw_pathname()
+            ),
+            format => format!("{} {}", format, self.raw_pathname()),
+        }
+    }
+}
+
+// Parse a six character octal file mode. Unset modes are all NUL.
+fn parse_file_mode(mode: &[char; 6]) -> Option<u32> {
+    if mode.iter().all(|c| *c == '\u{0}') {
+        return None;
+    }
+    let mode: String = mode.iter().collect();
+    u32::from_str_radix(&mode, 8).ok()
+}
+
+// Decode a path quoted by git as a C string literal. Octal escapes are
+// bytes of the UTF-8 encoded path.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted.trim_start_matches('"').trim_end_matches('"');
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut iter = inner.bytes().peekable();
+    while let Some(b) = iter.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match iter.next() {
+            Some(b'a') => bytes.push(0x07),
+            Some(b'b') => bytes.push(0x08),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b'v') => bytes.push(0x0b),
+            Some(b'f') => bytes.push(0x0c),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(d @ b'0'..=b'7') => {
+                let mut val = u32::from(d - b'0');
+                for _ in 0..2 {
+                    match iter.peek() {
+                        Some(d @ b'0'..=b'7') => {
+                            val = val * 8 + u32::from(d - b'0');
+                            iter.next();
+                        }
+                        _ => break,
+                    }
+                }
+                bytes.push(val as u8);
+            }
+            Some(c) => bytes.push(c),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
 impl TryFrom<&str> for Entry {
@@ -357,9 +722,12 @@ impl TryFrom<&str> for Entry {
     fn try_from(txt: &str) -> std::result::Result<Entry, Self::Error> {
         macro_rules! err {
             () => {
-                format_err!("bad entry format")
+                err!("bad entry format")
             };
-        };
+            ($reason:expr) => {
+                Error::from(StatusParseError::new(txt, $reason))
+            };
+        }
         let mut chars = txt.chars();
         let mut entry = Entry {
             ..Default::default()
@@ -425,11 +793,11 @@ impl TryFrom<&str> for Entry {
                 // <hI>
                 entry.object_name.1 = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
                 // <X><score>
-                entry.score.0 = (&mut chars).next().ok_or(err!())?;
+                entry.score.0 = chars.next().ok_or(err!())?;
                 let tmp: String = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
-                entry.score.1 = tmp.parse::<u8>().context(err!())?;
+                entry.score.1 = tmp.parse::<u8>().map_err(|_| err!())?;
                 // <path>
-                entry.path = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
+                entry.path = (&mut chars).take_while(|c| *c != '\t').collect();
                 // <origPath>
                 entry.orig_path = (&mut chars).collect();
             }
@@ -466,23 +834,33 @@ impl TryFrom<&str> for Entry {
                 // <h3>
                 entry.stage3.0 = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
                 // <path>
-                entry.path = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
+                entry.path = chars.collect();
             }
 
             // Untracked entry
             '?' => {
                 // <path>
-                entry.path = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
+                entry.path = chars.collect();
             }
 
             // Ignored entry
             '!' => {
                 // <path>
-                entry.path = (&mut chars).take_while(|c| !c.is_whitespace()).collect();
+                entry.path = chars.collect();
             }
 
-            _ => bail!("unknown entry format identifier (should be one of: 1 2 u ? !)"),
+            _ => return Err(err!("unknown entry format identifier (should be one of: 1 2 u ? !)")),
         };
+
+        // Decode quoted paths, keeping the raw paths
+        if entry.path.starts_with('"') {
+            let path = unquote(&entry.path);
+            entry.raw_path = std::mem::replace(&mut entry.path, path);
+        }
+        if entry.orig_path.starts_with('"') {
+            let path = unquote(&entry.orig_path);
+            entry.raw_orig_path = std::mem::replace(&mut entry.orig_path, path);
+        }
         Ok(entry)
     }
 }
@@ -491,7 +869,7 @@ impl TryFrom<&str> for Entry {
 mod tests {
     use super::*;
 
-    static OUT: &'static str = "# branch.oid dbcbc3608451f09fffef8f31a2a54da54aa13a87
+    static OUT: &str = "# branch.oid dbcbc3608451f09fffef8f31a2a54da54aa13a87
 # branch.head master
 # branch.upstream origin/master
 # branch.ab +1 -0
@@ -623,12 +1001,88 @@ mod tests {
         assert_eq!(Entry::try_from(ignored).expect("failed to parse ignored entry"), ig_want, "Ignored entry not parsed correctly");
     }
 
+    #[test]
+    fn entry_quoted_path() {
+        let changed = "1 .M N... 100644 100644 100644 567578ae6981902a62d42f69599a1101e33a0bba 567578ae6981902a62d42f69599a1101e33a0bba \"caf\\303\\251 \\\"menu\\\".txt\"";
+        let entry = Entry::try_from(changed).unwrap();
+        assert_eq!(entry.pathname(), "caf\u{e9} \"menu\".txt");
+        assert_eq!(entry.raw_pathname(), "\"caf\\303\\251 \\\"menu\\\".txt\"");
+        assert_eq!(entry.to_porcelain_v2(), changed);
+
+        let renamed = "2 R. N... 100644 100644 100644 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 R100 \"tab\\there\"\tplain name.txt";
+        let entry = Entry::try_from(renamed).unwrap();
+        assert_eq!(entry.pathname(), "tab\there");
+        assert_eq!(entry.raw_pathname(), "\"tab\\there\"");
+        assert_eq!(entry.orig_path(), "plain name.txt");
+        assert_eq!(entry.to_porcelain_v2(), renamed);
+
+        let untracked = Entry::try_from("? with space.txt").unwrap();
+        assert_eq!(untracked.pathname(), "with space.txt");
+        assert_eq!(untracked.raw_pathname(), "with space.txt");
+    }
+
+    #[test]
+    fn status_quoted_paths() {
+        let out = "? with space.txt\n? \"caf\\303\\251.txt\"\n! \"tab\\there\"\n";
+        let status = Status::try_from(out).unwrap();
+        assert_eq!(status.untracked, vec!["with space.txt", "caf\u{e9}.txt"]);
+        assert_eq!(status.ignored, vec!["tab\there"]);
+    }
+
+    #[test]
+    fn entry_short_code() {
+        let changed = "1 A. N... 000000 100644 100644 0000000000000000000000000000000000000000 df6d704ad8308efda4715321c69c9aff1fc95e0e TODO.md";
+        assert_eq!(Entry::try_from(changed).unwrap().short_code(), "A ");
+        let changed = "1 .M N... 100644 100644 100644 567578ae6981902a62d42f69599a1101e33a0bba 567578ae6981902a62d42f69599a1101e33a0bba README.md";
+        assert_eq!(Entry::try_from(changed).unwrap().short_code(), " M");
+        let renamed = "2 RM N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R100 LICENSE	LICENSE~";
+        assert_eq!(Entry::try_from(renamed).unwrap().short_code(), "RM");
+        assert_eq!(Entry::try_from("? ufile.txt").unwrap().short_code(), "??");
+        assert_eq!(Entry::try_from("! idir/").unwrap().short_code(), "!!");
+    }
+
+    #[test]
+    fn entry_file_mode_octal() {
+        let changed = "1 A. N... 000000 100644 100755 0000000000000000000000000000000000000000 df6d704ad8308efda4715321c69c9aff1fc95e0e TODO.md";
+        let entry = Entry::try_from(changed).unwrap();
+        assert_eq!(entry.file_mode_head_octal(), Some(0));
+        assert_eq!(entry.file_mode_index_octal(), Some(0o100644));
+        assert_eq!(entry.file_mode_worktree_octal(), Some(0o100755));
+
+        // Unmerged entries only have a worktree mode
+        let unmerged = "u MM N... 000000 100644 100644 100755 0000000000000000000000000000000000000000 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 LICENSE";
+        let entry = Entry::try_from(unmerged).unwrap();
+        assert_eq!(entry.file_mode_head_octal(), None);
+        assert_eq!(entry.file_mode_index_octal(), None);
+        assert_eq!(entry.file_mode_worktree_octal(), Some(0o100755));
+
+        assert_eq!(Entry::try_from("? ufile.txt").unwrap().file_mode_worktree_octal(), None);
+    }
+
     #[test]
     #[should_panic(expected = "unknown entry format identifier (should be one of: 1 2 u ? !)")]
     fn invalid_entry_parsing() {
         Entry::try_from("0 Foo/Bar.txt").unwrap();
     }
 
+    #[test]
+    fn status_parse_error() {
+        let err = Status::try_from("# branch.ab 1 -0").expect_err("malformed line was parsed");
+        let err = err.downcast_ref::<StatusParseError>().expect("not a StatusParseError");
+        assert_eq!(err.line(), "# branch.ab 1 -0");
+        assert_eq!(err.reason(), "bad status format");
+
+        let err = Status::try_from("# branch.foo bar").expect_err("unknown branch info was parsed");
+        let err = err.downcast_ref::<StatusParseError>().expect("not a StatusParseError");
+        assert_eq!(err.reason(), "unknown branch info");
+        assert_eq!(err.to_string(), "unknown branch info: # branch.foo bar");
+
+        let err = Status::try_from("2 R. N... 100644 100644 100644 288d 288d Rx LICENSE").expect_err("bad score was parsed");
+        let err = err.downcast_ref::<StatusParseError>().expect("not a StatusParseError");
+        assert_eq!(err.line(), "2 R. N... 100644 100644 100644 288d 288d Rx LICENSE");
+        assert_eq!(err.reason(), "bad entry format");
+    }
+
     #[test]
     fn status() {
         assert_eq!(Status::new(), Status { ..Default::default() }, "Status::new is not default");
@@ -671,6 +1125,7 @@ mod tests {
             upstream: (String::from("origin/master"), 1, 0),
             changed: vec![entry1, entry2],
             renamed: Vec::new(),
+            unmerged: Vec::new(),
             untracked: vec!["LICENSE~".to_string(), "Makefile".to_string()],
             ignored: Vec::new(),
         };
@@ -683,4 +1138,88 @@ mod tests {
         assert_eq!(status.upstream_behind(), Some(1));
         assert_eq!(status.upstream_ahead(), Some(0));
     }
+
+    #[test]
+    fn status_to_porcelain_v2() {
+        let status = Status::try_from(OUT).expect("failed to parse status");
+        assert_eq!(status.to_porcelain_v2(), OUT);
+        assert_eq!(Status::try_from(status.to_porcelain_v2().as_str()).unwrap(), status);
+
+        let out = "# branch.oid (initial)
+# branch.head master
+1 .M SCMU 160000 160000 160000 567578ae6981902a62d42f69599a1101e33a0bba 567578ae6981902a62d42f69599a1101e33a0bba sub
+2 R. N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R100 LICENSE	LICENSE~
+u UU N... 100644 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 README.md
+? Makefile
+! target/
+";
+        let status = Status::try_from(out).expect("failed to parse status");
+        assert_eq!(status.to_porcelain_v2(), out);
+        assert_eq!(Status::new().to_porcelain_v2(), "");
+    }
+
+    #[test]
+    fn status_iter() {
+        let out = "# branch.oid dbcbc3608451f09fffef8f31a2a54da54aa13a87
+# branch.head master
+1 A. N... 000000 100644 100644 0000000000000000000000000000000000000000 e47c0835424019d3cb9f3daf768eafbb2fd42044 Cargo.toml
+2 R. N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R100 LICENSE	LICENSE~
+u UU N... 100644 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 README.md
+? Makefile
+";
+        let status = Status::try_from(out).expect("failed to parse status");
+        assert_eq!(status.unmerged.len(), 1);
+        let paths: Vec<_> = status.iter().map(|e| e.pathname()).collect();
+        assert_eq!(paths, vec!["Cargo.toml", "LICENSE", "README.md"]);
+        assert!(status.iter().any(|e| e.is_unmerged()));
+        assert_eq!(Status::new().iter().count(), 0);
+    }
+
+    #[test]
+    fn status_renames() {
+        let out = "# branch.oid dbcbc3608451f09fffef8f31a2a54da54aa13a87
+# branch.head master
+1 A. N... 000000 100644 100644 0000000000000000000000000000000000000000 e47c0835424019d3cb9f3daf768eafbb2fd42044 Cargo.toml
+2 R. N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R100 LICENSE\tLICENSE~
+2 RM N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R87 src/new name.rs\tsrc/old.rs
+2 C. N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 C100 copy.txt\torig.txt
+";
+        let status = Status::try_from(out).expect("failed to parse status");
+        assert_eq!(
+            status.renames(),
+            vec![
+                ("LICENSE~".to_string(), "LICENSE".to_string(), 100),
+                ("src/old.rs".to_string(), "src/new name.rs".to_string(), 87),
+            ]
+        );
+        assert!(Status::new().renames().is_empty());
+    }
+
+    #[test]
+    fn status_diff() {
+        let before = Status::try_from(OUT).expect("failed to parse status");
+        let after = Status::try_from("# branch.oid dbcbc3608451f09fffef8f31a2a54da54aa13a87
+# branch.head master
+1 AM N... 000000 100644 100644 0000000000000000000000000000000000000000 e47c0835424019d3cb9f3daf768eafbb2fd42044 Cargo.toml
+2 R. N... 100644 100644 100644 288d723fce8678bcdcb40bfa844a6f815d625661 288d723fce8678bcdcb40bfa844a6f815d625661 R100 LICENSE\tLICENSE~
+? Makefile
+? build.rs
+! target/
+").expect("failed to parse status");
+
+        let delta = before.diff(&after);
+        assert_eq!(delta.changed.added, vec!["LICENSE"]);
+        assert_eq!(delta.changed.removed, vec!["README.md"]);
+        assert_eq!(delta.untracked.added, vec!["build.rs"]);
+        assert_eq!(delta.untracked.removed, vec!["LICENSE~"]);
+        assert_eq!(delta.ignored.added, vec!["target/"]);
+        assert!(delta.ignored.removed.is_empty());
+        assert!(!delta.is_empty());
+
+        let reverse = after.diff(&before);
+        assert_eq!(reverse.changed.added, delta.changed.removed);
+        assert_eq!(reverse.untracked.removed, delta.untracked.added);
+        assert!(before.diff(&before).is_empty());
+        assert_eq!(Status::new().diff(&Status::new()), StatusDelta::default());
+    }
 }
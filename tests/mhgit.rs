@@ -1,5 +1,19 @@
 extern crate mhgit;
 
+use mhgit::commands::{
+    AddOptions, BranchOptions, CommitFormat, CommitGraphOptions, CommitOptions, DiffOptions, LogOptions,
+    PushOptions, RemoteOptions, RevParseOptions, StatusOptions, TagListOptions, TagOptions, TagVerifyOptions,
+};
+use mhgit::{
+    CommandOptions, ConflictError, CorruptCommitGraphError, GitOut, MergeOutcome, Operation, OutputLimitError,
+    RepoState, Repository,
+};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::thread;
+
 // use mhgit::{commands::CloneOptions, Repository};
 
 #[test]
@@ -10,4 +24,1351 @@ fn test_mhgit() {
     // let _ = Repository::at("/home/m/testgit");
     // let status = repo.status().expect("failed to get git status");
     // println!(">>>> Status\n{:?}", status);
-}
\ No newline at end of file
+}
+
+// -----------------------------------------------------------------------------
+// Helpers
+
+/// Create an initialized repository in a fresh temporary directory.
+///
+/// The directory is named after the test, so tests don't share repositories.
+fn temp_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = env::temp_dir().join(format!("mhgit-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create temp dir");
+    let mut repo = Repository::at(&dir).expect("failed to open temp repo");
+    repo.init().expect("failed to init temp repo");
+    git(&dir, &["config", "user.name", "MHgit Test"]);
+    git(&dir, &["config", "user.email", "test@mhgit.rs"]);
+    git(&dir, &["config", "commit.gpgsign", "false"]);
+    (dir, repo)
+}
+
+/// Run git directly in `dir`, panicking on failure. Returns stdout.
+fn git(dir: &Path, args: &[&str]) -> String {
+    let out = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run git");
+    assert!(
+        out.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).expect("git output not utf-8")
+}
+
+/// Write `content` to `path` relative to `dir`, creating parent directories.
+fn write(dir: &Path, path: &str, content: &str) {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).expect("failed to create parent dir");
+    }
+    fs::write(path, content).expect("failed to write file");
+}
+
+// -----------------------------------------------------------------------------
+// Status
+
+#[test]
+fn status_pathspec() {
+    let (dir, repo) = temp_repo("status_pathspec");
+    write(&dir, "foo/a.txt", "a");
+    write(&dir, "bar/b.txt", "b");
+
+    let status = repo.status().expect("failed to get status");
+    assert_eq!(status.untracked, vec!["bar/", "foo/"]);
+
+    let status = StatusOptions::new()
+        .pathspec("foo")
+        .run(&repo)
+        .expect("failed to get path-scoped status");
+    assert_eq!(status.untracked, vec!["foo/"]);
+}
+
+#[test]
+fn run_raw() {
+    let (dir, repo) = temp_repo("run_raw");
+    write(&dir, "foo.txt", "foo");
+
+    let raw = StatusOptions::new().run_raw(&repo).unwrap();
+    assert!(raw.starts_with("# branch.oid (initial)\n"), "unexpected output: {}", raw);
+    assert!(raw.ends_with("? foo.txt\n"), "unexpected output: {}", raw);
+
+    // Output of commands parsed into () is kept too
+    let raw = AddOptions::new()
+        .pathspec("foo.txt")
+        .extra_args(&["--dry-run"])
+        .run_raw(&repo)
+        .unwrap();
+    assert_eq!(raw, "add 'foo.txt'\n");
+}
+
+#[test]
+fn run_full() {
+    let (dir, mut repo) = temp_repo("run_full");
+    repo.commit("First").unwrap();
+    git(&dir, &["branch", "ambiguous"]);
+    git(&dir, &["tag", "ambiguous"]);
+
+    let out = RevParseOptions::new().revision("ambiguous").run_full(&repo).unwrap();
+    assert!(out.success());
+    assert_eq!(out.stdout, git(&dir, &["rev-parse", "HEAD"]));
+    assert!(out.stderr.contains("refname 'ambiguous' is ambiguous"), "stderr: {}", out.stderr);
+
+    let out = RevParseOptions::new().verify(true).revision("missing").run_full(&repo).unwrap();
+    assert!(!out.success());
+    assert_eq!(out.code, Some(128));
+    assert_eq!(out.stdout, "");
+}
+
+// -----------------------------------------------------------------------------
+// Repository
+
+#[test]
+fn max_output_bytes() {
+    let (dir, mut repo) = temp_repo("max_output_bytes");
+    write(&dir, "foo.txt", "foo");
+    assert!(repo.status().is_ok());
+
+    let err = repo
+        .max_output_bytes(8)
+        .status()
+        .expect_err("status output should exceed limit");
+    assert!(
+        err.downcast_ref::<OutputLimitError>().is_some(),
+        "unexpected error: {}",
+        err
+    );
+    assert_eq!(err.to_string(), "git status output exceeded limit of 8 bytes");
+}
+
+#[test]
+fn locked() {
+    let (dir, mut repo) = temp_repo("locked");
+    repo.locked(true);
+
+    let repo = &repo;
+    let dir = &dir;
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                s.spawn(move || -> Result<(), failure::Error> {
+                    let file = format!("file{}.txt", i);
+                    write(dir, &file, "content");
+                    AddOptions::new().pathspec(&file).run(repo)?;
+                    CommitOptions::new()
+                        .message(&file)
+                        .allow_empty(true)
+                        .run(repo)?;
+                    Ok(())
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap().expect("concurrent command failed");
+        }
+    });
+
+    assert_eq!(git(dir, &["rev-list", "--count", "HEAD"]).trim(), "8");
+    assert_eq!(git(dir, &["ls-files"]).lines().count(), 8);
+    assert!(repo.status().unwrap().changed.is_empty());
+}
+
+#[test]
+fn branch_contains() {
+    let (dir, mut repo) = temp_repo("branch_contains");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    let first = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    repo.commit("Second").unwrap();
+    let second = git(&dir, &["rev-parse", "HEAD"]);
+
+    assert!(repo.branch_contains("main", first.trim()).unwrap());
+    assert!(repo.branch_contains("feature", first.trim()).unwrap());
+    assert!(repo.branch_contains("feature", second.trim()).unwrap());
+    assert!(!repo.branch_contains("main", second.trim()).unwrap());
+    assert!(repo.branch_contains("nosuchbranch", first.trim()).is_err());
+
+    assert_eq!(repo.branches_containing(first.trim()).unwrap(), vec!["feature", "main"]);
+    assert_eq!(repo.branches_containing(second.trim()).unwrap(), vec!["feature"]);
+}
+
+#[test]
+fn delete_merged_branches() {
+    let (dir, mut repo) = temp_repo("delete_merged_branches");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["branch", "merged"]);
+    git(&dir, &["branch", "protected"]);
+    git(&dir, &["checkout", "-q", "-b", "unmerged"]);
+    repo.commit("Second").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "current", "main"]);
+
+    let deleted = repo.delete_merged_branches("main", &["protected"]).unwrap();
+    assert_eq!(deleted, vec!["merged"]);
+    assert_eq!(
+        git(&dir, &["branch", "--format=%(refname:short)"]),
+        "current\nmain\nprotected\nunmerged\n"
+    );
+}
+
+#[test]
+fn delete_merged_branches_detached() {
+    let (dir, mut repo) = temp_repo("delete_merged_branches_detached");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["branch", "merged"]);
+    git(&dir, &["checkout", "-q", "--detach"]);
+
+    let deleted = repo.delete_merged_branches("main", &[]).unwrap();
+    assert_eq!(deleted, vec!["merged"]);
+    assert_eq!(git(&dir, &["for-each-ref", "--format=%(refname:short)", "refs/heads"]), "main\n");
+}
+
+#[test]
+fn branch_list_detached() {
+    let (dir, mut repo) = temp_repo("branch_list_detached");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["branch", "feature"]);
+    git(&dir, &["checkout", "-q", "--detach"]);
+
+    assert_eq!(repo.branches_containing("HEAD").unwrap(), vec!["feature", "main"]);
+    assert_eq!(BranchOptions::list().run(&repo).unwrap(), vec!["feature", "main"]);
+}
+
+#[test]
+fn orphan_branch() {
+    let (dir, mut repo) = temp_repo("orphan_branch");
+    write(&dir, "foo.txt", "foo");
+    repo.add().unwrap().commit("First").unwrap();
+
+    repo.orphan_branch("gh-pages", true).unwrap();
+    assert!(git(&dir, &["branch", "--list", "gh-pages"]).is_empty());
+    assert!(git(&dir, &["ls-files"]).is_empty());
+    assert!(!dir.join("foo.txt").exists());
+    write(&dir, "index.html", "<html></html>");
+    repo.add().unwrap().commit("Pages").unwrap();
+
+    assert_eq!(git(&dir, &["symbolic-ref", "--short", "HEAD"]), "gh-pages\n");
+    assert_eq!(git(&dir, &["rev-list", "--count", "HEAD"]), "1\n");
+    assert_eq!(git(&dir, &["ls-files"]), "index.html\n");
+
+    repo.orphan_branch("kept", false).unwrap();
+    assert_eq!(git(&dir, &["symbolic-ref", "--short", "HEAD"]), "kept\n");
+    assert_eq!(git(&dir, &["ls-files"]), "index.html\n");
+}
+
+#[test]
+fn alias() {
+    let (dir, repo) = temp_repo("alias");
+    git(&dir, &["config", "alias.st", "status --short"]);
+
+    assert!(repo.is_alias("st").unwrap());
+    assert_eq!(repo.alias_target("st").unwrap(), Some("status --short".to_string()));
+    assert!(!repo.is_alias("status").unwrap());
+    assert_eq!(repo.alias_target("status").unwrap(), None);
+}
+
+#[test]
+fn remote_default_branch() {
+    let (remote_dir, _) = temp_repo("remote_default_branch-remote");
+    git(&remote_dir, &["checkout", "-q", "-b", "main"]);
+    write(&remote_dir, "foo.txt", "foo");
+    git(&remote_dir, &["add", "foo.txt"]);
+    git(&remote_dir, &["commit", "-q", "-m", "Initial commit"]);
+
+    let (dir, mut repo) = temp_repo("remote_default_branch");
+    repo.remote("origin", remote_dir.to_str().unwrap())
+        .unwrap()
+        .fetch()
+        .unwrap();
+
+    // Fetching doesn't set refs/remotes/origin/HEAD, so the remote is queried
+    assert_eq!(repo.remote_default_branch("origin").unwrap(), "main");
+
+    git(&dir, &["remote", "set-head", "origin", "main"]);
+    assert_eq!(repo.remote_default_branch("origin").unwrap(), "main");
+
+    assert!(repo.remote_default_branch("nosuchremote").is_err());
+}
+
+#[test]
+fn merge_abort() {
+    let (remote_dir, _) = temp_repo("merge_abort-remote");
+    git(&remote_dir, &["checkout", "-q", "-b", "main"]);
+    write(&remote_dir, "foo.txt", "first");
+    git(&remote_dir, &["add", "foo.txt"]);
+    git(&remote_dir, &["commit", "-q", "-m", "First"]);
+
+    let (dir, mut repo) = temp_repo("merge_abort");
+    git(&dir, &["config", "pull.rebase", "false"]);
+    repo.remote("origin", remote_dir.to_str().unwrap()).unwrap().fetch().unwrap();
+    git(&dir, &["checkout", "-q", "-b", "main", "origin/main"]);
+    write(&dir, "foo.txt", "local");
+    repo.commit_all("Local").unwrap();
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+
+    write(&remote_dir, "foo.txt", "remote");
+    git(&remote_dir, &["commit", "-q", "-a", "-m", "Remote"]);
+    assert!(repo.pull().is_err());
+    assert!(repo.is_merging().unwrap());
+
+    repo.merge_abort().unwrap();
+    assert!(!repo.is_merging().unwrap());
+    assert_eq!(git(&dir, &["status", "--porcelain"]), "");
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), head);
+    let err = repo.merge_abort().unwrap_err();
+    assert_eq!(err.to_string(), "no merge in progress to abort");
+}
+
+#[test]
+fn remote_url() {
+    let (dir, mut repo) = temp_repo("remote_url");
+    repo.remote("origin", "https://mirror.example.com/repo.git").unwrap();
+    assert_eq!(repo.remote_url("origin", false).unwrap(), "https://mirror.example.com/repo.git");
+    assert_eq!(repo.remote_url("origin", true).unwrap(), "https://mirror.example.com/repo.git");
+
+    git(&dir, &["remote", "set-url", "--push", "origin", "git@example.com:repo.git"]);
+    assert_eq!(repo.remote_url("origin", false).unwrap(), "https://mirror.example.com/repo.git");
+    assert_eq!(repo.remote_url("origin", true).unwrap(), "git@example.com:repo.git");
+    assert!(repo.remote_url("nosuchremote", false).is_err());
+}
+
+#[test]
+fn fetch_refspec() {
+    let (remote_dir, mut remote) = temp_repo("fetch_refspec-remote");
+    git(&remote_dir, &["checkout", "-q", "-b", "main"]);
+    remote.commit("First").unwrap();
+    git(&remote_dir, &["checkout", "-q", "-b", "other"]);
+    remote.commit("Second").unwrap();
+
+    let (dir, mut repo) = temp_repo("fetch_refspec");
+    repo.remote("origin", remote_dir.to_str().unwrap())
+        .unwrap()
+        .fetch_refspec("origin", "main:refs/remotes/origin/main")
+        .unwrap();
+    assert_eq!(
+        git(&dir, &["for-each-ref", "--format=%(refname)"]),
+        "refs/remotes/origin/main\n"
+    );
+    assert_eq!(
+        git(&dir, &["rev-parse", "origin/main"]),
+        git(&remote_dir, &["rev-parse", "main"])
+    );
+}
+
+#[test]
+fn rebase_status() {
+    let (dir, mut repo) = temp_repo("rebase_status");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    write(&dir, "foo.txt", "first");
+    repo.commit_all("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    write(&dir, "foo.txt", "feature");
+    repo.commit_all("Feature").unwrap().commit("Feature 2").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    write(&dir, "foo.txt", "main");
+    repo.commit_all("Main").unwrap();
+    assert_eq!(repo.rebase_status().unwrap(), None);
+
+    let status = Command::new("git")
+        .current_dir(&dir)
+        .args(["rebase", "-q", "--merge", "main", "feature"])
+        .output()
+        .expect("failed to run git");
+    assert!(!status.status.success(), "rebase didn't stop at conflict");
+    let rebase = repo.rebase_status().unwrap().expect("no rebase in progress");
+    assert_eq!(rebase.onto, git(&dir, &["rev-parse", "main"]).trim());
+    assert_eq!((rebase.step, rebase.total), (1, 2));
+    assert_eq!(rebase.head_name.as_deref(), Some("refs/heads/feature"));
+
+    git(&dir, &["rebase", "--abort"]);
+    assert_eq!(repo.rebase_status().unwrap(), None);
+}
+
+#[test]
+fn remote_add_refspec() {
+    let (dir, repo) = temp_repo("remote_add_refspec");
+    RemoteOptions::add()
+        .mirror("fetch")
+        .name("backup")
+        .url("../backup.git")
+        .fetch_refspec("+refs/heads/*:refs/heads/*")
+        .fetch_refspec("+refs/tags/*:refs/tags/*")
+        .run(&repo)
+        .unwrap();
+    assert_eq!(
+        git(&dir, &["config", "--get-all", "remote.backup.fetch"]),
+        "+refs/heads/*:refs/heads/*\n+refs/tags/*:refs/tags/*\n"
+    );
+
+    // The refspecs are configured whichever way the command is run
+    let mut opts = RemoteOptions::add();
+    opts.name("raw").url("../raw.git").fetch_refspec("+refs/heads/main:refs/heads/main");
+    opts.run_raw(&repo).unwrap();
+    opts.name("full").url("../full.git");
+    assert!(opts.run_full(&repo).unwrap().success());
+    for remote in ["raw", "full"] {
+        let key = format!("remote.{}.fetch", remote);
+        assert_eq!(git(&dir, &["config", "--get-all", &key]), "+refs/heads/main:refs/heads/main\n");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn remote_add_refspec_failure() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (dir, mut repo) = temp_repo("remote_add_refspec_failure");
+    // A git wrapper failing to set config, after the remote is added
+    write(&dir, "fakegit", "#!/bin/sh\n[ \"$1\" = config ] && [ \"$2\" = --replace-all ] && exit 1\nexec git \"$@\"\n");
+    fs::set_permissions(dir.join("fakegit"), fs::Permissions::from_mode(0o755)).unwrap();
+    repo.git_binary(dir.join("fakegit"));
+
+    let err = RemoteOptions::add()
+        .name("backup")
+        .url("../backup.git")
+        .fetch_refspec("+refs/heads/*:refs/heads/*")
+        .run(&repo)
+        .unwrap_err();
+    assert!(err.to_string().contains("removed remote backup"), "unexpected error: {}", err);
+    assert_eq!(git(&dir, &["remote"]), "");
+}
+
+#[test]
+fn reset() {
+    let (dir, mut repo) = temp_repo("reset");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    let first = git(&dir, &["rev-parse", "HEAD"]);
+    write(&dir, "foo.txt", "second");
+    repo.add().unwrap().commit("Second").unwrap();
+    let second = git(&dir, &["rev-parse", "HEAD"]);
+
+    repo.reset_soft("HEAD~1").unwrap();
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), first);
+    assert_eq!(repo.status().unwrap().changed[0].modified_state(), ('M', '.'));
+
+    repo.reset_mixed(second.trim()).unwrap();
+    write(&dir, "foo.txt", "changed");
+    repo.reset_hard(first.trim()).unwrap();
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), first);
+    assert_eq!(fs::read_to_string(dir.join("foo.txt")).unwrap(), "first");
+    assert!(repo.status().unwrap().changed.is_empty());
+}
+
+#[test]
+fn diff_renames() {
+    let (dir, mut repo) = temp_repo("diff_renames");
+    write(&dir, "old name.txt", "some content\nover\nseveral lines\n");
+    repo.add().unwrap().commit("First").unwrap();
+    git(&dir, &["mv", "old name.txt", "new name.txt"]);
+
+    let diff = DiffOptions::new()
+        .cached(true)
+        .name_status(true)
+        .find_renames(Some(50))
+        .run(&repo)
+        .unwrap();
+    assert_eq!(diff.files.len(), 1);
+    assert_eq!(diff.files[0].status, 'R');
+    assert_eq!(diff.files[0].score, 100);
+    assert_eq!(diff.files[0].path, "new name.txt");
+    assert_eq!(diff.files[0].orig_path.as_deref(), Some("old name.txt"));
+}
+
+#[test]
+fn blob_oid() {
+    let (dir, mut repo) = temp_repo("blob_oid");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    write(&dir, "foo.txt", "second");
+    repo.add().unwrap();
+
+    let oid = repo.blob_oid("foo.txt").unwrap();
+    let status = repo.status().unwrap();
+    assert_eq!(status.changed[0].object_name_index(), oid);
+    assert_ne!(status.changed[0].object_name_head(), oid);
+    assert_eq!(git(&dir, &["cat-file", "-t", &oid]), "blob\n");
+
+    // Not written to the object database
+    write(&dir, "foo.txt", "third");
+    let oid = repo.blob_oid("foo.txt").unwrap();
+    let exists = Command::new("git")
+        .current_dir(&dir)
+        .args(["cat-file", "-e", &oid])
+        .status()
+        .expect("failed to run git");
+    assert!(!exists.success());
+    assert!(repo.blob_oid("missing.txt").is_err());
+}
+
+#[test]
+fn commit_all() {
+    let (dir, mut repo) = temp_repo("commit_all");
+    write(&dir, "foo.txt", "foo");
+    repo.commit_all("Add foo").unwrap();
+    assert_eq!(git(&dir, &["log", "--format=%s"]), "Add foo\n");
+    assert_eq!(git(&dir, &["ls-files"]), "foo.txt\n");
+    assert_eq!(git(&dir, &["status", "--porcelain"]), "");
+
+    fs::remove_file(dir.join("foo.txt")).unwrap();
+    repo.commit_all("Remove foo").unwrap().commit_all("Nothing").unwrap();
+    assert_eq!(git(&dir, &["ls-files"]), "");
+    assert_eq!(git(&dir, &["rev-list", "--count", "HEAD"]), "3\n");
+}
+
+#[test]
+fn commit_allow_empty() {
+    let (dir, mut repo) = temp_repo("commit_allow_empty");
+    write(&dir, "foo.txt", "first");
+    repo.commit_all("First").unwrap();
+
+    // Nothing to commit
+    repo.commit("Empty").unwrap();
+    assert!(CommitOptions::new().message("Empty builder").run(&repo).is_err());
+    CommitOptions::new_like_repository()
+        .message("Empty builder")
+        .run(&repo)
+        .unwrap();
+    assert_eq!(git(&dir, &["rev-list", "--count", "HEAD"]), "3\n");
+}
+
+#[test]
+fn changed_files_between() {
+    let (dir, mut repo) = temp_repo("changed_files_between");
+    write(&dir, "foo.txt", "first");
+    write(&dir, "bar.txt", "first");
+    repo.commit_all("First").unwrap();
+    git(&dir, &["tag", "v1"]);
+    write(&dir, "foo.txt", "second");
+    repo.commit_all("Second").unwrap();
+    write(&dir, "src/baz qux.txt", "third");
+    repo.commit_all("Third").unwrap();
+    git(&dir, &["tag", "v2"]);
+    write(&dir, "bar.txt", "fourth");
+    repo.commit_all("Fourth").unwrap();
+
+    assert_eq!(repo.changed_files_between("v1", "v2").unwrap(), vec!["foo.txt", "src/baz qux.txt"]);
+    assert_eq!(repo.changed_files_between("v2", "HEAD").unwrap(), vec!["bar.txt"]);
+    assert!(repo.changed_files_between("v2", "v2").unwrap().is_empty());
+    assert!(repo.changed_files_between("v1", "nope").is_err());
+}
+
+#[test]
+fn conflicted_files() {
+    let (dir, mut repo) = temp_repo("conflicted_files");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    write(&dir, "foo.txt", "first");
+    write(&dir, "bar baz.txt", "first");
+    repo.commit_all("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    write(&dir, "foo.txt", "feature");
+    write(&dir, "bar baz.txt", "feature");
+    repo.commit_all("Feature").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    write(&dir, "foo.txt", "main");
+    write(&dir, "bar baz.txt", "main");
+    write(&dir, "other.txt", "main");
+    repo.commit_all("Main").unwrap();
+    assert!(repo.conflicted_files().unwrap().is_empty());
+
+    let merge = Command::new("git")
+        .current_dir(&dir)
+        .args(["merge", "-q", "feature"])
+        .output()
+        .expect("failed to run git");
+    assert!(!merge.status.success(), "merge didn't stop at conflict");
+    assert_eq!(repo.conflicted_files().unwrap(), vec!["bar baz.txt", "foo.txt"]);
+}
+
+#[test]
+fn state() {
+    let (dir, mut repo) = temp_repo("state");
+    assert_eq!(repo.state().unwrap().oid, "");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    write(&dir, "foo.txt", "first");
+    repo.commit_all("First").unwrap();
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+    assert_eq!(
+        repo.state().unwrap(),
+        RepoState {
+            branch: Some("main".to_string()),
+            oid: head.trim_end().to_string(),
+            ..Default::default()
+        }
+    );
+
+    // Track a branch one commit behind, and add a commit on each side
+    git(&dir, &["branch", "upstream"]);
+    git(&dir, &["branch", "-q", "--set-upstream-to=upstream"]);
+    write(&dir, "foo.txt", "main");
+    repo.commit_all("Main").unwrap();
+    git(&dir, &["checkout", "-q", "upstream"]);
+    write(&dir, "foo.txt", "upstream");
+    repo.commit_all("Upstream").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    write(&dir, "bar.txt", "untracked");
+    let state = repo.state().unwrap();
+    assert_eq!(state.upstream.as_deref(), Some("upstream"));
+    assert_eq!((state.ahead, state.behind), (1, 1));
+    assert!(!state.dirty && state.untracked);
+    assert_eq!(state.operation, None);
+
+    assert_eq!(repo.try_merge("upstream", false).unwrap(), MergeOutcome::Conflicted);
+    let state = repo.state().unwrap();
+    assert!(state.dirty);
+    assert_eq!(state.operation, Some(Operation::Merge));
+
+    repo.merge_abort().unwrap();
+    git(&dir, &["checkout", "-q", "--detach"]);
+    let state = repo.state().unwrap();
+    assert_eq!((state.branch, state.upstream, state.operation), (None, None, None));
+}
+
+#[test]
+fn try_merge() {
+    let (dir, mut repo) = temp_repo("try_merge");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    write(&dir, "foo.txt", "first");
+    repo.commit_all("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    write(&dir, "foo.txt", "feature");
+    repo.commit_all("Feature").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "other", "main"]);
+    write(&dir, "bar.txt", "other");
+    repo.commit_all("Other").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    write(&dir, "foo.txt", "main");
+    repo.commit_all("Main").unwrap();
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+
+    assert_eq!(repo.try_merge("feature", true).unwrap(), MergeOutcome::Conflicted);
+    assert_eq!(git(&dir, &["status", "--porcelain"]), "");
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), head);
+
+    assert_eq!(repo.try_merge("other", true).unwrap(), MergeOutcome::Merged);
+    assert_eq!(git(&dir, &["rev-list", "--count", "HEAD"]), "4\n");
+
+    assert_eq!(repo.try_merge("feature", false).unwrap(), MergeOutcome::Conflicted);
+    assert_eq!(repo.conflicted_files().unwrap(), vec!["foo.txt"]);
+
+    assert!(repo.try_merge("no-such-branch", true).is_err());
+}
+
+#[test]
+fn commit_date() {
+    let (dir, repo) = temp_repo("commit_date");
+    CommitOptions::new()
+        .message("Pinned")
+        .allow_empty(true)
+        .date("@1592042400 +0200")
+        .run(&repo)
+        .unwrap();
+    assert_eq!(git(&dir, &["log", "-1", "--format=%at %ai"]), "1592042400 2020-06-13 12:00:00 +0200\n");
+
+    CommitOptions::new()
+        .message("Now")
+        .allow_empty(true)
+        .date_now()
+        .run(&repo)
+        .unwrap();
+    let out = git(&dir, &["log", "-1", "--format=%at %ct"]);
+    let (author, committer) = out.trim().split_once(' ').unwrap();
+    let diff = author.parse::<i64>().unwrap() - committer.parse::<i64>().unwrap();
+    assert!(diff.abs() <= 5, "author date {} is not now", author);
+}
+
+#[test]
+fn commit_exists() {
+    let (dir, mut repo) = temp_repo("commit_exists");
+    assert!(!repo.commit_exists("HEAD").unwrap());
+    write(&dir, "foo.txt", "foo");
+    repo.add().unwrap().commit("First").unwrap();
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+
+    assert!(repo.commit_exists("HEAD").unwrap());
+    assert!(repo.commit_exists(head.trim()).unwrap());
+    assert!(!repo.commit_exists("HEAD^{tree}").unwrap());
+    assert!(!repo.commit_exists("HEAD:foo.txt").unwrap());
+    assert!(!repo.commit_exists("1234567890123456789012345678901234567890").unwrap());
+    assert!(!repo.commit_exists("bogus").unwrap());
+}
+
+#[test]
+fn check_attr() {
+    let (dir, repo) = temp_repo("check_attr");
+    write(&dir, ".gitattributes", "*.png filter=lfs -diff\ndocs/ export-ignore\n");
+    let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+    assert_eq!(
+        repo.check_attr(&["filter", "diff", "text"], "assets/logo.png").unwrap(),
+        vec![pair("filter", "lfs"), pair("diff", "unset"), pair("text", "unspecified")]
+    );
+    assert_eq!(
+        repo.check_attr_all("assets/logo.png").unwrap(),
+        vec![pair("diff", "unset"), pair("filter", "lfs")]
+    );
+    assert!(repo.check_attr_all("README.md").unwrap().is_empty());
+}
+
+#[test]
+fn lfs_tracked() {
+    let (dir, repo) = temp_repo("lfs_tracked");
+    assert!(repo.lfs_tracked().unwrap().is_empty());
+    write(&dir, ".gitattributes", "*.psd filter=lfs diff=lfs merge=lfs -text\n*.rs text\n");
+    write(&dir, "src/.gitattributes", "*.png filter=lfs diff=lfs merge=lfs -text\n");
+    assert_eq!(repo.lfs_tracked().unwrap(), vec!["*.psd"]);
+    assert_eq!(Repository::at(dir.join("src")).unwrap().lfs_tracked().unwrap(), vec!["*.psd"]);
+}
+
+#[test]
+fn checkout() {
+    let (dir, mut repo) = temp_repo("checkout");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    let first = git(&dir, &["rev-parse", "HEAD"]);
+    git(&dir, &["branch", "feature"]);
+    write(&dir, "foo.txt", "second");
+    repo.add().unwrap().commit("Second").unwrap();
+
+    repo.checkout("feature").unwrap();
+    assert_eq!(git(&dir, &["symbolic-ref", "--short", "HEAD"]), "feature\n");
+    assert_eq!(fs::read_to_string(dir.join("foo.txt")).unwrap(), "first");
+
+    repo.checkout("main").unwrap().checkout(first.trim()).unwrap();
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), first);
+    assert!(repo.checkout("missing").is_err());
+}
+
+#[test]
+fn checkout_detached() {
+    let (dir, mut repo) = temp_repo("checkout_detached");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap().commit("Second").unwrap();
+    let first = git(&dir, &["rev-parse", "HEAD~1"]);
+    assert!(!repo.status().unwrap().is_detached());
+
+    repo.checkout_detached("HEAD~1").unwrap();
+    let status = repo.status().unwrap();
+    assert!(status.is_detached());
+    assert_eq!(status.branch_oid(), first.trim_end());
+
+    // Branches are detached at too, rather than switched to
+    repo.checkout_detached("main").unwrap();
+    assert!(repo.status().unwrap().is_detached());
+
+    let err = repo.checkout_detached("missing").unwrap_err();
+    assert!(err.to_string().contains("no such commit"), "unexpected error: {}", err);
+}
+
+#[test]
+fn create_branch() {
+    let (dir, mut repo) = temp_repo("create_branch");
+    repo.commit("First").unwrap().commit("Second").unwrap();
+    let first = git(&dir, &["rev-parse", "HEAD~1"]);
+    let second = git(&dir, &["rev-parse", "HEAD"]);
+
+    repo.create_branch("old", Some("HEAD~1")).unwrap();
+    assert_eq!(git(&dir, &["symbolic-ref", "--short", "HEAD"]), "old\n");
+    assert_eq!(git(&dir, &["rev-parse", "old"]), first);
+
+    repo.checkout("old").unwrap().create_branch("new", None).unwrap();
+    assert_eq!(git(&dir, &["symbolic-ref", "--short", "HEAD"]), "new\n");
+    assert_eq!(git(&dir, &["rev-parse", "new"]), first);
+    assert!(repo.create_branch("new", Some(second.trim())).is_err());
+}
+
+#[test]
+fn commit_files() {
+    let (dir, mut repo) = temp_repo("commit_files");
+    write(&dir, "café.txt", "first");
+    write(&dir, "old name.txt", "a file which is renamed");
+    repo.add().unwrap().commit("First").unwrap();
+    assert_eq!(
+        repo.commit_files("HEAD").unwrap(),
+        vec![('A', "café.txt".to_string()), ('A', "old name.txt".to_string())]
+    );
+
+    git(&dir, &["mv", "old name.txt", "new name.txt"]);
+    write(&dir, "café.txt", "second");
+    repo.add().unwrap().commit("Second").unwrap();
+    assert_eq!(
+        repo.commit_files("HEAD").unwrap(),
+        vec![('M', "café.txt".to_string()), ('R', "new name.txt".to_string())]
+    );
+}
+
+#[test]
+fn checkout_paths() {
+    let (dir, mut repo) = temp_repo("checkout_paths");
+    write(&dir, "foo.txt", "first");
+    write(&dir, "bar.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    write(&dir, "foo.txt", "second");
+    write(&dir, "bar.txt", "second");
+    repo.add().unwrap().commit("Second").unwrap();
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+
+    repo.checkout_paths("HEAD~1", &["foo.txt"]).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("foo.txt")).unwrap(), "first");
+    assert_eq!(fs::read_to_string(dir.join("bar.txt")).unwrap(), "second");
+    assert_eq!(git(&dir, &["rev-parse", "HEAD"]), head);
+    assert_eq!(git(&dir, &["diff", "--cached", "--name-only"]), "foo.txt\n");
+}
+
+#[test]
+fn rev_parse() {
+    let (dir, mut repo) = temp_repo("rev_parse");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap().commit("Second").unwrap();
+    let oids = RevParseOptions::new()
+        .revisions(["HEAD", "HEAD~1", "main"])
+        .run(&repo)
+        .unwrap();
+    assert_eq!(oids.len(), 3);
+    assert_eq!(oids[0], git(&dir, &["rev-parse", "HEAD"]).trim());
+    assert_eq!(oids[1], git(&dir, &["rev-parse", "HEAD~1"]).trim());
+    assert_eq!(oids[0], oids[2]);
+
+    let names = RevParseOptions::new().abbrev_ref(true).revision("HEAD").run(&repo).unwrap();
+    assert_eq!(names, vec!["main"]);
+    let short = RevParseOptions::new().short(10).revision("HEAD").run(&repo).unwrap();
+    assert_eq!(short[0], &oids[0][..10]);
+    assert!(RevParseOptions::new().verify(true).revision("missing").run(&repo).is_err());
+}
+
+#[test]
+fn show_file() {
+    let (dir, mut repo) = temp_repo("show_file");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    write(&dir, "foo.txt", "second");
+    repo.add().unwrap().commit("Second").unwrap();
+
+    assert_eq!(repo.show_file("HEAD~1", "foo.txt").unwrap(), b"first");
+    assert_eq!(repo.show_file("HEAD", "foo.txt").unwrap(), b"second");
+
+    let err = repo.show_file("HEAD", "bar.txt").expect_err("missing file was shown");
+    assert_eq!(err.to_string(), "failed to show bar.txt at revision HEAD");
+}
+
+#[test]
+fn file_line_count() {
+    let (dir, mut repo) = temp_repo("file_line_count");
+    write(&dir, "foo.txt", "one\ntwo\nthree\n");
+    repo.add().unwrap().commit("First").unwrap();
+    write(&dir, "foo.txt", "one\ntwo\nthree\nfour\nfive");
+
+    assert_eq!(repo.file_line_count("foo.txt", Some("HEAD")).unwrap(), 3);
+    assert_eq!(repo.file_line_count("foo.txt", None).unwrap(), 5);
+    assert!(repo.file_line_count("bar.txt", Some("HEAD")).is_err());
+    assert!(repo.file_line_count("bar.txt", None).is_err());
+}
+
+#[test]
+fn is_at() {
+    let (dir, mut repo) = temp_repo("is_at");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+    git(&dir, &["tag", "v1.0"]);
+    assert!(repo.is_at("v1.0").unwrap());
+    assert!(repo.is_at("HEAD").unwrap());
+
+    // Untracked files are ignored
+    write(&dir, "bar.txt", "bar");
+    assert!(repo.is_at("v1.0").unwrap());
+
+    write(&dir, "foo.txt", "changed");
+    assert!(!repo.is_at("v1.0").unwrap());
+    git(&dir, &["add", "foo.txt"]);
+    assert!(!repo.is_at("v1.0").unwrap());
+
+    repo.commit("Second").unwrap();
+    assert!(!repo.is_at("v1.0").unwrap());
+    assert!(repo.is_at("HEAD").unwrap());
+    assert!(repo.is_at("nosuchrev").is_err());
+}
+
+#[test]
+fn is_ignored() {
+    let (dir, mut repo) = temp_repo("is_ignored");
+    write(&dir, ".gitignore", "target/\n*.log\n!keep.log\n");
+    write(&dir, "tracked.log", "tracked");
+    git(&dir, &["add", "-f", ".gitignore", "tracked.log"]);
+    repo.commit("First").unwrap();
+
+    assert!(repo.is_ignored("target/debug/app").unwrap());
+    assert!(repo.is_ignored("build.log").unwrap());
+    assert!(!repo.is_ignored("keep.log").unwrap());
+    assert!(!repo.is_ignored("tracked.log").unwrap());
+    assert!(!repo.is_ignored("src/main.rs").unwrap());
+}
+
+#[test]
+fn operation_in_progress() {
+    let (dir, mut repo) = temp_repo("operation_in_progress");
+    repo.commit("First").unwrap();
+    let state = |repo: &Repository| {
+        (
+            repo.is_merging().unwrap(),
+            repo.is_rebasing().unwrap(),
+            repo.is_cherry_picking().unwrap(),
+        )
+    };
+    assert_eq!(state(&repo), (false, false, false));
+
+    let git_dir = dir.join(".git");
+    fs::write(git_dir.join("MERGE_HEAD"), "").unwrap();
+    assert_eq!(state(&repo), (true, false, false));
+    fs::remove_file(git_dir.join("MERGE_HEAD")).unwrap();
+
+    fs::create_dir(git_dir.join("rebase-merge")).unwrap();
+    assert_eq!(state(&repo), (false, true, false));
+    fs::remove_dir(git_dir.join("rebase-merge")).unwrap();
+
+    // git am, not a rebase
+    fs::create_dir(git_dir.join("rebase-apply")).unwrap();
+    assert_eq!(state(&repo), (false, false, false));
+    fs::write(git_dir.join("rebase-apply/rebasing"), "").unwrap();
+    assert_eq!(state(&repo), (false, true, false));
+    fs::remove_dir_all(git_dir.join("rebase-apply")).unwrap();
+
+    fs::write(git_dir.join("CHERRY_PICK_HEAD"), "").unwrap();
+    assert_eq!(state(&repo), (false, false, true));
+}
+
+#[test]
+fn is_bare() {
+    let (dir, repo) = temp_repo("is_bare");
+    assert!(repo.is_inside_work_tree().unwrap());
+    assert!(!repo.is_bare().unwrap());
+
+    let bare_dir = dir.join("bare.git");
+    fs::create_dir(&bare_dir).unwrap();
+    git(&bare_dir, &["init", "-q", "--bare"]);
+    let bare = Repository::at(&bare_dir).unwrap();
+    assert!(!bare.is_inside_work_tree().unwrap());
+    assert!(bare.is_bare().unwrap());
+}
+
+#[test]
+fn push_porcelain() {
+    let (remote_dir, _) = temp_repo("push_porcelain-remote");
+    let (_, mut repo) = temp_repo("push_porcelain");
+    repo.commit("First").unwrap();
+    repo.remote("origin", remote_dir.to_str().unwrap()).unwrap();
+
+    let results = PushOptions::new()
+        .porcelain(true)
+        .remote("origin")
+        .refspec("HEAD:refs/heads/main")
+        .run(&repo)
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].flag, '*');
+    assert_eq!(results[0].to, "refs/heads/main");
+
+    CommitOptions::new()
+        .amend(true)
+        .allow_empty(true)
+        .message("Amended")
+        .run(&repo)
+        .unwrap();
+    let results = PushOptions::new()
+        .porcelain(true)
+        .remote("origin")
+        .refspec("HEAD:refs/heads/main")
+        .run(&repo)
+        .unwrap();
+    assert!(results[0].is_rejected());
+    assert_eq!(results[0].summary, "[rejected] (non-fast-forward)");
+}
+
+#[test]
+fn verify_commit() {
+    let (_, mut repo) = temp_repo("verify_commit");
+    repo.commit("Unsigned").unwrap();
+    let sig = repo.verify_commit("HEAD").unwrap();
+    assert!(!sig.valid);
+    assert_eq!(sig.key_id, "");
+    assert!(repo.verify_commit("HEAD^{tree}").is_err());
+    let err = repo.verify_commit("missing").unwrap_err();
+    assert_eq!(err.to_string(), "can't verify missing, no such commit");
+
+    // Run like other commands, allowed in read-only repositories and with
+    // limited output
+    repo.read_only(true);
+    assert!(!repo.verify_commit("HEAD").unwrap().valid);
+    assert!(!repo.max_output_bytes(64).verify_commit("HEAD").unwrap().valid);
+    let err = repo.max_output_bytes(4).verify_commit("HEAD").unwrap_err();
+    assert!(err.downcast_ref::<OutputLimitError>().is_some(), "unexpected error: {}", err);
+}
+
+#[test]
+fn worktree() {
+    let (dir, mut repo) = temp_repo("worktree");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["branch", "feature"]);
+
+    let wt_dir = env::temp_dir().join(format!("mhgit-worktree-wt-{}", process::id()));
+    let _ = fs::remove_dir_all(&wt_dir);
+    let wt = repo.worktree_add(wt_dir.to_str().unwrap(), "feature").unwrap();
+    write(&wt_dir, "foo.txt", "foo");
+    assert_eq!(wt.status().unwrap().untracked, vec!["foo.txt"]);
+    assert_eq!(git(&wt_dir, &["symbolic-ref", "--short", "HEAD"]), "feature\n");
+
+    let worktrees = repo.worktree_list().unwrap();
+    assert_eq!(worktrees.len(), 2);
+    assert_eq!(worktrees[0].path, fs::canonicalize(&dir).unwrap());
+    assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+    assert_eq!(worktrees[1].path, fs::canonicalize(&wt_dir).unwrap());
+    assert_eq!(worktrees[1].branch.as_deref(), Some("feature"));
+    assert_eq!(worktrees[0].head, worktrees[1].head);
+
+    // A branch can't be checked out in two worktrees
+    assert!(repo.worktree_add("other", "main").is_err());
+}
+
+#[test]
+fn commit_graph_verify() {
+    let (dir, mut repo) = temp_repo("commit_graph_verify");
+    repo.commit("First").unwrap().commit("Second").unwrap();
+    CommitGraphOptions::verify().run(&repo).unwrap();
+    CommitGraphOptions::write(true).run(&repo).unwrap();
+    CommitGraphOptions::verify().run(&repo).unwrap();
+
+    // Overwrite the signature of the file
+    let file = dir.join(".git/objects/info/commit-graph");
+    let mut data = fs::read(&file).unwrap();
+    data[..4].copy_from_slice(b"XXXX");
+    let mut perms = fs::metadata(&file).unwrap().permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    perms.set_readonly(false);
+    fs::set_permissions(&file, perms).unwrap();
+    fs::write(&file, data).unwrap();
+
+    let err = CommitGraphOptions::verify().run(&repo).expect_err("corrupt commit-graph verified");
+    assert!(err.downcast_ref::<CorruptCommitGraphError>().is_some(), "unexpected error: {}", err);
+    CommitGraphOptions::write(true).run(&repo).unwrap();
+    CommitGraphOptions::verify().run(&repo).unwrap();
+}
+
+#[test]
+fn git_dir() {
+    let (dir, mut repo) = temp_repo("git_dir");
+    assert_eq!(repo.git_dir().unwrap(), dir.join(".git"));
+
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    let wt_dir = env::temp_dir().join(format!("mhgit-git_dir-wt-{}", process::id()));
+    let _ = fs::remove_dir_all(&wt_dir);
+    git(&dir, &["branch", "feature"]);
+    let wt = repo.worktree_add(wt_dir.to_str().unwrap(), "feature").unwrap();
+    let wt_git_dir = wt.git_dir().unwrap();
+    assert!(wt_git_dir.is_absolute());
+    assert_eq!(
+        fs::canonicalize(wt_git_dir).unwrap(),
+        fs::canonicalize(dir.join(".git/worktrees")).unwrap().join(wt_dir.file_name().unwrap())
+    );
+}
+
+#[test]
+fn log() {
+    let (_, mut repo) = temp_repo("log");
+    repo.commit("First").unwrap();
+    CommitOptions::new()
+        .message("Second\n\nWith a body")
+        .allow_empty(true)
+        .run(&repo)
+        .unwrap();
+
+    let commits = LogOptions::new().run(&repo).unwrap();
+    let subjects: Vec<_> = commits.iter().map(|c| c.subject.as_str()).collect();
+    assert_eq!(subjects, vec!["Second", "First"]);
+    assert_eq!(commits[0].author, "MHgit Test <test@mhgit.rs>");
+    assert!(!commits[0].date.is_empty());
+
+    let commits = LogOptions::new()
+        .format(CommitFormat::OneLine)
+        .max_count(1)
+        .run(&repo)
+        .unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].oid.len(), 40);
+    assert_eq!(commits[0].subject, "Second");
+}
+
+#[test]
+fn log_first_parent() {
+    let (dir, mut repo) = temp_repo("log_first_parent");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    repo.commit("Feature 1").unwrap().commit("Feature 2").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    repo.commit("Second").unwrap();
+    git(&dir, &["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"]);
+
+    let subjects = |first_parent: bool| -> Vec<String> {
+        LogOptions::new()
+            .format(CommitFormat::OneLine)
+            .first_parent(first_parent)
+            .run(&repo)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.subject)
+            .collect()
+    };
+    assert_eq!(subjects(false).len(), 5);
+    assert_eq!(subjects(true), vec!["Merge feature", "Second", "First"]);
+}
+
+#[test]
+fn diff_merge_first_parent() {
+    let (dir, mut repo) = temp_repo("diff_merge_first_parent");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    write(&dir, "feature.txt", "feature");
+    repo.add().unwrap().commit("Feature").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    write(&dir, "main.txt", "main");
+    repo.add().unwrap().commit("Second").unwrap();
+    git(&dir, &["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"]);
+
+    let files = |parent: &str| -> Vec<String> {
+        DiffOptions::new()
+            .name_status(true)
+            .against(parent)
+            .against("HEAD")
+            .run(&repo)
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .collect()
+    };
+    // The mainline only gained the feature, the feature branch gained main
+    assert_eq!(files("HEAD^1"), vec!["feature.txt"]);
+    assert_eq!(files("HEAD^2"), vec!["main.txt"]);
+}
+
+#[test]
+fn log_detailed() {
+    let (dir, mut repo) = temp_repo("log_detailed");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    repo.commit("Feature").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+    repo.commit("Second").unwrap();
+    git(&dir, &["merge", "-q", "--no-ff", "-m", "Merge feature\n\nWith a body\nover two lines", "feature"]);
+
+    let commits = LogOptions::new()
+        .format(CommitFormat::Detailed)
+        .run(&repo)
+        .unwrap();
+    assert_eq!(commits.len(), 4);
+    assert_eq!(commits[0].subject, "Merge feature");
+    assert_eq!(commits[0].body, "With a body\nover two lines");
+    assert_eq!(commits[0].parents.len(), 2);
+    assert_eq!(commits[0].parents[1], git(&dir, &["rev-parse", "feature"]).trim());
+    assert_eq!(commits[0].committer, "MHgit Test <test@mhgit.rs>");
+    assert!(commits[3].parents.is_empty());
+}
+
+// -----------------------------------------------------------------------------
+// Stash
+
+#[test]
+fn stash_pop() {
+    let (dir, mut repo) = temp_repo("stash_pop");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+
+    write(&dir, "foo.txt", "changed");
+    repo.stash().unwrap();
+    assert_eq!(fs::read_to_string(dir.join("foo.txt")).unwrap(), "first");
+    let stashes = repo.stash_list().unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].name, "stash@{0}");
+
+    repo.stash_pop().unwrap();
+    assert_eq!(fs::read_to_string(dir.join("foo.txt")).unwrap(), "changed");
+    assert!(repo.stash_list().unwrap().is_empty());
+}
+
+#[test]
+fn stash_pop_keep_index() {
+    let (dir, mut repo) = temp_repo("stash_pop_keep_index");
+    write(&dir, "foo.txt", "first");
+    write(&dir, "bar.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+
+    write(&dir, "foo.txt", "staged");
+    git(&dir, &["add", "foo.txt"]);
+    write(&dir, "bar.txt", "unstaged");
+    repo.stash().unwrap();
+
+    repo.stash_pop_keep_index().unwrap();
+    assert_eq!(git(&dir, &["diff", "--cached", "--name-only"]), "foo.txt\n");
+    assert_eq!(git(&dir, &["diff", "--name-only"]), "bar.txt\n");
+    assert!(repo.stash_list().unwrap().is_empty());
+}
+
+#[test]
+fn stash_pop_conflict() {
+    let (dir, mut repo) = temp_repo("stash_pop_conflict");
+    write(&dir, "foo.txt", "first");
+    repo.add().unwrap().commit("First").unwrap();
+
+    write(&dir, "foo.txt", "stashed");
+    repo.stash().unwrap();
+    write(&dir, "foo.txt", "committed");
+    repo.add().unwrap().commit("Second").unwrap();
+
+    let err = repo.stash_pop().expect_err("conflicting pop succeeded");
+    assert!(err.downcast_ref::<ConflictError>().is_some(), "unexpected error: {}", err);
+    assert_eq!(repo.stash_list().unwrap().len(), 1);
+}
+
+// -----------------------------------------------------------------------------
+// Tag
+
+#[test]
+fn tag_list() {
+    let (dir, mut repo) = temp_repo("tag_list");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap().tag("v1.9.0").unwrap().tag("other").unwrap();
+    repo.commit("Second").unwrap().tag("v1.10.0").unwrap();
+    git(&dir, &["checkout", "-q", "-b", "feature"]);
+    repo.commit("Third").unwrap().tag("v2.0.0-rc").unwrap();
+    git(&dir, &["checkout", "-q", "main"]);
+
+    let tags = TagListOptions::new()
+        .pattern("v*")
+        .merged("main")
+        .sort("-version:refname")
+        .run(&repo)
+        .unwrap();
+    assert_eq!(tags, vec!["v1.10.0", "v1.9.0"]);
+    let tags = TagListOptions::new().points_at("main~1").run(&repo).unwrap();
+    assert_eq!(tags, vec!["other", "v1.9.0"]);
+}
+
+#[test]
+fn tag_exists() {
+    let (dir, mut repo) = temp_repo("tag_exists");
+    repo.commit("First").unwrap().tag("v1.0.0").unwrap();
+    git(&dir, &["branch", "v2.0.0"]);
+    assert!(repo.tag_exists("v1.0.0").unwrap());
+    assert!(!repo.tag_exists("v2.0.0").unwrap());
+    assert!(!repo.tag_exists("v3.0.0").unwrap());
+}
+
+#[test]
+fn latest_tag() {
+    let (dir, mut repo) = temp_repo("latest_tag");
+    git(&dir, &["checkout", "-q", "-b", "main"]);
+    repo.commit("First").unwrap();
+    assert_eq!(repo.latest_tag().unwrap(), None);
+
+    repo.tag("v1.0.0").unwrap().commit("Second").unwrap();
+    git(&dir, &["tag", "-a", "-m", "Release", "v1.1.0"]);
+    repo.commit("Third").unwrap();
+    assert_eq!(repo.latest_tag().unwrap().as_deref(), Some("v1.1.0"));
+
+    git(&dir, &["checkout", "-q", "--orphan", "other"]);
+    repo.commit("Unrelated").unwrap();
+    assert_eq!(repo.latest_tag().unwrap(), None);
+}
+
+#[test]
+fn tag_without_message() {
+    let (dir, mut repo) = temp_repo("tag_without_message");
+    repo.commit("Initial commit").unwrap();
+
+    // An annotated tag without a message would wait for an editor
+    let err = TagOptions::add()
+        .tagname("v1.0")
+        .extra_args(&["-a"])
+        .run(&repo)
+        .expect_err("tag without message succeeded");
+    assert!(err.to_string().starts_with("git tag"), "unexpected error: {}", err);
+    let err = TagOptions::add()
+        .tagname("v1.0")
+        .run(&repo)
+        .expect_err("tag without message or lightweight succeeded");
+    assert!(err.to_string().contains("would open an editor"), "unexpected error: {}", err);
+    assert!(git(&dir, &["tag", "--list"]).is_empty());
+
+    // Signing by default must not turn a lightweight tag into an annotated one
+    git(&dir, &["config", "tag.gpgSign", "true"]);
+    TagOptions::add().lightweight(true).tagname("v1.0").run(&repo).unwrap();
+    repo.tag("v1.1").unwrap();
+    assert_eq!(git(&dir, &["cat-file", "-t", "v1.0"]), "commit\n");
+    assert_eq!(git(&dir, &["cat-file", "-t", "v1.1"]), "commit\n");
+}
+
+#[test]
+fn read_only_reads() {
+    let (origin, mut origin_repo) = temp_repo("read_only_reads_origin");
+    git(&origin, &["checkout", "-q", "-b", "main"]);
+    origin_repo.commit("First").unwrap();
+    let (dir, mut repo) = temp_repo("read_only_reads");
+    git(&dir, &["remote", "add", "origin", origin.to_str().unwrap()]);
+    git(&dir, &["fetch", "-q", "origin"]);
+    git(&dir, &["checkout", "-q", "-b", "main", "origin/main"]);
+    git(&dir, &["config", "alias.co", "checkout"]);
+    git(&dir, &["tag", "-a", "-m", "Unsigned tag", "v1.0"]);
+    write(&dir, "foo.txt", "stashed");
+    git(&dir, &["add", "foo.txt"]);
+    git(&dir, &["stash", "-q"]);
+    write(&dir, "bar.txt", "bar");
+
+    repo.read_only(true);
+    assert!(repo.config_list().unwrap().iter().any(|(k, _)| k == "alias.co"));
+    assert_eq!(repo.alias_target("co").unwrap().as_deref(), Some("checkout"));
+    assert_eq!(repo.blob_oid("bar.txt").unwrap(), git(&dir, &["hash-object", "bar.txt"]).trim_end());
+    assert_eq!(repo.remote_url("origin", false).unwrap(), origin.to_str().unwrap());
+    assert!(repo.remote_info("origin").is_ok());
+    assert_eq!(repo.branches_containing("HEAD").unwrap(), vec!["main"]);
+    assert_eq!(repo.stash_list().unwrap().len(), 1);
+    assert_eq!(repo.worktree_list().unwrap().len(), 1);
+    assert_eq!(TagListOptions::new().run(&repo).unwrap(), vec!["v1.0"]);
+    assert!(!TagVerifyOptions::new().tagname("v1.0").run(&repo).unwrap());
+
+    assert!(repo.stash_pop().is_err());
+    assert!(repo.create_branch("feature", None).is_err());
+}
+
+#[test]
+fn tag_verify() {
+    let (_, mut repo) = temp_repo("tag_verify");
+    repo.commit("Initial commit").unwrap();
+    TagOptions::add()
+        .msg("Unsigned tag")
+        .tagname("v1.0")
+        .run(&repo)
+        .unwrap();
+
+    assert!(!TagOptions::verify().tagname("v1.0").run(&repo).unwrap());
+    assert!(!TagVerifyOptions::new().tagname("v1.0").run_full(&repo).unwrap().success());
+    assert!(TagVerifyOptions::new().tagname("v9.9").run(&repo).is_err());
+    assert!(TagVerifyOptions::new().tagname("v9.9").run_raw(&repo).is_err());
+    assert!(TagVerifyOptions::new().tagname("v9.9").run_full(&repo).is_err());
+
+    // Git only says to check stderr when printing it
+    repo.gitout(GitOut::Print);
+    assert!(!TagVerifyOptions::new().tagname("v1.0").run(&repo).unwrap());
+    let err = TagVerifyOptions::new().tagname("v9.9").run(&repo).unwrap_err();
+    assert_eq!(err.to_string(), "can't verify tag v9.9, no such tag");
+}